@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS `data_exports` (
+  `id` VARCHAR(255) NOT NULL,
+  `user_id` VARCHAR(255) NOT NULL,
+  `status` VARCHAR(32) NOT NULL,
+  `format_version` INT NOT NULL,
+  `file_name` VARCHAR(255) NULL,
+  `error` TEXT NULL,
+  `expires_at` DATETIME NULL,
+  `created_at` DATETIME NOT NULL,
+  `updated_at` DATETIME NOT NULL,
+  PRIMARY KEY (`id`),
+  INDEX `fk_data_exports_users_idx` (`user_id` ASC),
+  CONSTRAINT `fk_data_exports_users`
+    FOREIGN KEY (`user_id`)
+    REFERENCES `users` (`id`)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION)
+ENGINE = InnoDB DEFAULT CHARSET=utf8mb4
+COMMENT = '';
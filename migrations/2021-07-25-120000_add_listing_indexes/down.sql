@@ -0,0 +1,6 @@
+DROP INDEX `idx_users_name` ON `users`;
+DROP INDEX `idx_users_created_at` ON `users`;
+DROP INDEX `idx_photos_is_public_created_at` ON `photos`;
+DROP INDEX `idx_photos_user_id_created_at` ON `photos`;
+DROP INDEX `idx_photos_updated_at` ON `photos`;
+DROP INDEX `idx_photos_created_at` ON `photos`;
@@ -0,0 +1,6 @@
+CREATE INDEX `idx_photos_created_at` ON `photos` (`created_at`);
+CREATE INDEX `idx_photos_updated_at` ON `photos` (`updated_at`);
+CREATE INDEX `idx_photos_user_id_created_at` ON `photos` (`user_id`, `created_at`);
+CREATE INDEX `idx_photos_is_public_created_at` ON `photos` (`is_public`, `created_at`);
+CREATE INDEX `idx_users_created_at` ON `users` (`created_at`);
+CREATE INDEX `idx_users_name` ON `users` (`name`);
@@ -0,0 +1,51 @@
+ALTER TABLE `photos` DROP FOREIGN KEY `fk_photos_users`;
+ALTER TABLE `data_exports` DROP FOREIGN KEY `fk_data_exports_users`;
+
+ALTER TABLE `data_exports` CHANGE COLUMN `user_id` `user_id_bin` BINARY(16) NOT NULL;
+ALTER TABLE `data_exports` ADD COLUMN `user_id` VARCHAR(255) NULL AFTER `id`;
+UPDATE `data_exports` SET `user_id` = LOWER(CONCAT_WS('-', SUBSTR(HEX(`user_id_bin`), 1, 8), SUBSTR(HEX(`user_id_bin`), 9, 4), SUBSTR(HEX(`user_id_bin`), 13, 4), SUBSTR(HEX(`user_id_bin`), 17, 4), SUBSTR(HEX(`user_id_bin`), 21)));
+ALTER TABLE `data_exports` DROP INDEX `fk_data_exports_users_idx`, DROP COLUMN `user_id_bin`;
+ALTER TABLE `data_exports`
+  MODIFY COLUMN `user_id` VARCHAR(255) NOT NULL,
+  ADD INDEX `fk_data_exports_users_idx` (`user_id` ASC);
+
+ALTER TABLE `photos`
+  CHANGE COLUMN `id` `id_bin` BINARY(16) NOT NULL,
+  CHANGE COLUMN `user_id` `user_id_bin` BINARY(16) NOT NULL;
+ALTER TABLE `photos`
+  ADD COLUMN `id` VARCHAR(255) NULL FIRST,
+  ADD COLUMN `user_id` VARCHAR(255) NULL AFTER `id`;
+UPDATE `photos` SET
+  `id` = LOWER(CONCAT_WS('-', SUBSTR(HEX(`id_bin`), 1, 8), SUBSTR(HEX(`id_bin`), 9, 4), SUBSTR(HEX(`id_bin`), 13, 4), SUBSTR(HEX(`id_bin`), 17, 4), SUBSTR(HEX(`id_bin`), 21))),
+  `user_id` = LOWER(CONCAT_WS('-', SUBSTR(HEX(`user_id_bin`), 1, 8), SUBSTR(HEX(`user_id_bin`), 9, 4), SUBSTR(HEX(`user_id_bin`), 13, 4), SUBSTR(HEX(`user_id_bin`), 17, 4), SUBSTR(HEX(`user_id_bin`), 21)));
+ALTER TABLE `photos`
+  DROP INDEX `idx_photos_user_id_created_at`,
+  DROP INDEX `fk_photos_users_idx`,
+  DROP PRIMARY KEY,
+  DROP COLUMN `id_bin`,
+  DROP COLUMN `user_id_bin`;
+ALTER TABLE `photos`
+  MODIFY COLUMN `id` VARCHAR(255) NOT NULL,
+  MODIFY COLUMN `user_id` VARCHAR(255) NOT NULL,
+  ADD PRIMARY KEY (`id`),
+  ADD INDEX `fk_photos_users_idx` (`user_id` ASC),
+  ADD INDEX `idx_photos_user_id_created_at` (`user_id`, `created_at`);
+
+ALTER TABLE `users` CHANGE COLUMN `id` `id_bin` BINARY(16) NOT NULL;
+ALTER TABLE `users` ADD COLUMN `id` VARCHAR(255) NULL FIRST;
+UPDATE `users` SET `id` = LOWER(CONCAT_WS('-', SUBSTR(HEX(`id_bin`), 1, 8), SUBSTR(HEX(`id_bin`), 9, 4), SUBSTR(HEX(`id_bin`), 13, 4), SUBSTR(HEX(`id_bin`), 17, 4), SUBSTR(HEX(`id_bin`), 21)));
+ALTER TABLE `users` DROP PRIMARY KEY, DROP COLUMN `id_bin`;
+ALTER TABLE `users`
+  MODIFY COLUMN `id` VARCHAR(255) NOT NULL,
+  ADD PRIMARY KEY (`id`);
+
+ALTER TABLE `photos` ADD CONSTRAINT `fk_photos_users`
+  FOREIGN KEY (`user_id`)
+  REFERENCES `users` (`id`)
+  ON DELETE NO ACTION
+  ON UPDATE NO ACTION;
+ALTER TABLE `data_exports` ADD CONSTRAINT `fk_data_exports_users`
+  FOREIGN KEY (`user_id`)
+  REFERENCES `users` (`id`)
+  ON DELETE CASCADE
+  ON UPDATE NO ACTION;
@@ -0,0 +1,47 @@
+-- ids were hyphenated UUID text, they become the 16 bytes of the same UUID
+ALTER TABLE `photos` DROP FOREIGN KEY `fk_photos_users`;
+ALTER TABLE `data_exports` DROP FOREIGN KEY `fk_data_exports_users`;
+
+ALTER TABLE `users` ADD COLUMN `id_bin` BINARY(16) NULL FIRST;
+UPDATE `users` SET `id_bin` = UNHEX(REPLACE(`id`, '-', ''));
+ALTER TABLE `users` DROP PRIMARY KEY, DROP COLUMN `id`;
+ALTER TABLE `users`
+  CHANGE COLUMN `id_bin` `id` BINARY(16) NOT NULL,
+  ADD PRIMARY KEY (`id`);
+
+ALTER TABLE `photos`
+  ADD COLUMN `id_bin` BINARY(16) NULL FIRST,
+  ADD COLUMN `user_id_bin` BINARY(16) NULL AFTER `id_bin`;
+UPDATE `photos` SET
+  `id_bin` = UNHEX(REPLACE(`id`, '-', '')),
+  `user_id_bin` = UNHEX(REPLACE(`user_id`, '-', ''));
+ALTER TABLE `photos`
+  DROP INDEX `idx_photos_user_id_created_at`,
+  DROP INDEX `fk_photos_users_idx`,
+  DROP PRIMARY KEY,
+  DROP COLUMN `id`,
+  DROP COLUMN `user_id`;
+ALTER TABLE `photos`
+  CHANGE COLUMN `id_bin` `id` BINARY(16) NOT NULL,
+  CHANGE COLUMN `user_id_bin` `user_id` BINARY(16) NOT NULL,
+  ADD PRIMARY KEY (`id`),
+  ADD INDEX `fk_photos_users_idx` (`user_id` ASC),
+  ADD INDEX `idx_photos_user_id_created_at` (`user_id`, `created_at`);
+
+ALTER TABLE `data_exports` ADD COLUMN `user_id_bin` BINARY(16) NULL AFTER `user_id`;
+UPDATE `data_exports` SET `user_id_bin` = UNHEX(REPLACE(`user_id`, '-', ''));
+ALTER TABLE `data_exports` DROP INDEX `fk_data_exports_users_idx`, DROP COLUMN `user_id`;
+ALTER TABLE `data_exports`
+  CHANGE COLUMN `user_id_bin` `user_id` BINARY(16) NOT NULL,
+  ADD INDEX `fk_data_exports_users_idx` (`user_id` ASC);
+
+ALTER TABLE `photos` ADD CONSTRAINT `fk_photos_users`
+  FOREIGN KEY (`user_id`)
+  REFERENCES `users` (`id`)
+  ON DELETE NO ACTION
+  ON UPDATE NO ACTION;
+ALTER TABLE `data_exports` ADD CONSTRAINT `fk_data_exports_users`
+  FOREIGN KEY (`user_id`)
+  REFERENCES `users` (`id`)
+  ON DELETE CASCADE
+  ON UPDATE NO ACTION;
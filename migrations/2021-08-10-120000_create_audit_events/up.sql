@@ -0,0 +1,17 @@
+-- no foreign keys, events are kept after the actor or the entity is deleted
+CREATE TABLE IF NOT EXISTS `audit_events` (
+  `id` BINARY(16) NOT NULL,
+  `actor_id` BINARY(16) NOT NULL,
+  `action` VARCHAR(64) NOT NULL,
+  `entity_type` VARCHAR(32) NOT NULL,
+  `entity_id` VARCHAR(255) NOT NULL,
+  `changes` TEXT NOT NULL,
+  `request_id` VARCHAR(255) NOT NULL,
+  `created_at` DATETIME NOT NULL,
+  PRIMARY KEY (`id`),
+  INDEX `idx_audit_events_created_at` (`created_at`),
+  INDEX `idx_audit_events_actor_id_created_at` (`actor_id`, `created_at`),
+  INDEX `idx_audit_events_entity_created_at` (`entity_type`, `entity_id`, `created_at`),
+  INDEX `idx_audit_events_request_id` (`request_id`))
+ENGINE = InnoDB DEFAULT CHARSET=utf8mb4
+COMMENT = '';
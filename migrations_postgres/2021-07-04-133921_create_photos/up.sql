@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS photos (
+  id VARCHAR(255) NOT NULL PRIMARY KEY,
+  user_id VARCHAR(255) NOT NULL REFERENCES users (id) ON DELETE NO ACTION ON UPDATE NO ACTION,
+  url VARCHAR(255) NOT NULL,
+  is_public BOOLEAN NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP NOT NULL
+);
+CREATE INDEX fk_photos_users_idx ON photos (user_id);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS data_exports (
+  id VARCHAR(255) NOT NULL PRIMARY KEY,
+  user_id VARCHAR(255) NOT NULL REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+  status VARCHAR(32) NOT NULL,
+  format_version INT NOT NULL,
+  file_name VARCHAR(255) NULL,
+  error TEXT NULL,
+  expires_at TIMESTAMP NULL,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP NOT NULL
+);
+CREATE INDEX fk_data_exports_users_idx ON data_exports (user_id);
@@ -0,0 +1,14 @@
+ALTER TABLE photos DROP CONSTRAINT photos_user_id_fkey;
+ALTER TABLE data_exports DROP CONSTRAINT data_exports_user_id_fkey;
+
+ALTER TABLE data_exports
+  ALTER COLUMN user_id TYPE VARCHAR(255) USING encode(user_id, 'hex')::uuid::text;
+ALTER TABLE photos
+  ALTER COLUMN id TYPE VARCHAR(255) USING encode(id, 'hex')::uuid::text,
+  ALTER COLUMN user_id TYPE VARCHAR(255) USING encode(user_id, 'hex')::uuid::text;
+ALTER TABLE users ALTER COLUMN id TYPE VARCHAR(255) USING encode(id, 'hex')::uuid::text;
+
+ALTER TABLE photos ADD CONSTRAINT photos_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE NO ACTION ON UPDATE NO ACTION;
+ALTER TABLE data_exports ADD CONSTRAINT data_exports_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION;
@@ -0,0 +1,15 @@
+-- ids were hyphenated UUID text, they become the 16 bytes of the same UUID
+ALTER TABLE photos DROP CONSTRAINT photos_user_id_fkey;
+ALTER TABLE data_exports DROP CONSTRAINT data_exports_user_id_fkey;
+
+ALTER TABLE users ALTER COLUMN id TYPE BYTEA USING decode(replace(id, '-', ''), 'hex');
+ALTER TABLE photos
+  ALTER COLUMN id TYPE BYTEA USING decode(replace(id, '-', ''), 'hex'),
+  ALTER COLUMN user_id TYPE BYTEA USING decode(replace(user_id, '-', ''), 'hex');
+ALTER TABLE data_exports
+  ALTER COLUMN user_id TYPE BYTEA USING decode(replace(user_id, '-', ''), 'hex');
+
+ALTER TABLE photos ADD CONSTRAINT photos_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE NO ACTION ON UPDATE NO ACTION;
+ALTER TABLE data_exports ADD CONSTRAINT data_exports_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION;
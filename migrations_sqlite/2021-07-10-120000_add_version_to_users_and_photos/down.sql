@@ -0,0 +1,3 @@
+-- DROP COLUMN needs SQLite 3.35 or later
+ALTER TABLE photos DROP COLUMN version;
+ALTER TABLE users DROP COLUMN version;
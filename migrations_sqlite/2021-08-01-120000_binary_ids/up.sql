@@ -0,0 +1,56 @@
+-- SQLite can't change a column's type, so every table is rebuilt. `uuid_to_bin` is registered
+-- on the connection by `ddb::migrations::run_pending`.
+CREATE TABLE users_new (
+  id BLOB NOT NULL PRIMARY KEY,
+  name VARCHAR(255) NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO users_new (id, name, created_at, updated_at, version)
+  SELECT uuid_to_bin(id), name, created_at, updated_at, version FROM users;
+
+CREATE TABLE photos_new (
+  id BLOB NOT NULL PRIMARY KEY,
+  user_id BLOB NOT NULL REFERENCES users_new (id) ON DELETE NO ACTION ON UPDATE NO ACTION,
+  url VARCHAR(255) NOT NULL,
+  is_public BOOLEAN NOT NULL,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO photos_new (id, user_id, url, is_public, created_at, updated_at, version)
+  SELECT uuid_to_bin(id), uuid_to_bin(user_id), url, is_public, created_at, updated_at, version FROM photos;
+
+CREATE TABLE data_exports_new (
+  id VARCHAR(255) NOT NULL PRIMARY KEY,
+  user_id BLOB NOT NULL REFERENCES users_new (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+  status VARCHAR(32) NOT NULL,
+  format_version INTEGER NOT NULL,
+  file_name VARCHAR(255) NULL,
+  error TEXT NULL,
+  expires_at TIMESTAMP NULL,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP NOT NULL
+);
+INSERT INTO data_exports_new
+  (id, user_id, status, format_version, file_name, error, expires_at, created_at, updated_at)
+  SELECT id, uuid_to_bin(user_id), status, format_version, file_name, error, expires_at, created_at, updated_at
+  FROM data_exports;
+
+DROP TABLE data_exports;
+DROP TABLE photos;
+DROP TABLE users;
+-- renaming updates the REFERENCES clauses of the other new tables as well
+ALTER TABLE users_new RENAME TO users;
+ALTER TABLE photos_new RENAME TO photos;
+ALTER TABLE data_exports_new RENAME TO data_exports;
+
+CREATE INDEX fk_photos_users_idx ON photos (user_id);
+CREATE INDEX fk_data_exports_users_idx ON data_exports (user_id);
+CREATE INDEX idx_photos_created_at ON photos (created_at);
+CREATE INDEX idx_photos_updated_at ON photos (updated_at);
+CREATE INDEX idx_photos_user_id_created_at ON photos (user_id, created_at);
+CREATE INDEX idx_photos_is_public_created_at ON photos (is_public, created_at);
+CREATE INDEX idx_users_created_at ON users (created_at);
+CREATE INDEX idx_users_name ON users (name);
@@ -0,0 +1,15 @@
+-- no foreign keys, events are kept after the actor or the entity is deleted
+CREATE TABLE IF NOT EXISTS audit_events (
+  id BLOB NOT NULL PRIMARY KEY,
+  actor_id BLOB NOT NULL,
+  action VARCHAR(64) NOT NULL,
+  entity_type VARCHAR(32) NOT NULL,
+  entity_id VARCHAR(255) NOT NULL,
+  changes TEXT NOT NULL,
+  request_id VARCHAR(255) NOT NULL,
+  created_at TIMESTAMP NOT NULL
+);
+CREATE INDEX idx_audit_events_created_at ON audit_events (created_at);
+CREATE INDEX idx_audit_events_actor_id_created_at ON audit_events (actor_id, created_at);
+CREATE INDEX idx_audit_events_entity_created_at ON audit_events (entity_type, entity_id, created_at);
+CREATE INDEX idx_audit_events_request_id ON audit_events (request_id);
@@ -0,0 +1,359 @@
+use std::env;
+use std::fmt::Display;
+use std::fs::File;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use dotenv::dotenv;
+use serde_json::json;
+
+use rust_graphql_diesel_sample::config::Config;
+use rust_graphql_diesel_sample::ddb::{self, DaoError};
+use rust_graphql_diesel_sample::domain;
+use rust_graphql_diesel_sample::domain::clock::SystemClock;
+use rust_graphql_diesel_sample::domain::id::{PhotoId, TimeOrderedIdGenerator, UserId};
+use rust_graphql_diesel_sample::import::{self, ImportRow};
+use rust_graphql_diesel_sample::repository::Repositories;
+
+const USAGE: &str = "usage: admin [--json] <command>
+
+commands:
+  users list
+  users search <name>
+  users show <user_id>
+  users force-delete <user_id>           delete a user together with their photos
+  photos toggle-visibility <photo_id>
+  photos reassign <from_user_id> <to_user_id>
+  photos import <user_id> <file>          import a .json array or .csv with url and isPublic (or is_public) columns";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Table,
+    Json,
+}
+
+fn main() {
+    dotenv().ok();
+
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    let format = match args.iter().position(|v| v == "--json") {
+        Some(i) => {
+            args.remove(i);
+            Format::Json
+        }
+        None => Format::Table,
+    };
+
+    let config = Config::load().unwrap_or_else(|e| exit_with("failed to load configuration", e));
+    let pool = ddb::new_pool(&config.database)
+        .unwrap_or_else(|e| exit_with("failed to connect to the database", e));
+    let repositories = pool
+        .get()
+        .map(Repositories::sql)
+        .unwrap_or_else(|e| exit_with("failed to connect to the database", e));
+
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = match args.as_slice() {
+        ["users", "list"] => list_users(&repositories, format),
+        ["users", "search", name] => search_users(&repositories, name, format),
+        ["users", "show", id] => show_user(&repositories, parse_id(id), format),
+        ["users", "force-delete", id] => force_delete_user(&repositories, parse_id(id), format),
+        ["photos", "toggle-visibility", id] => {
+            toggle_visibility(&repositories, parse_id(id), format)
+        }
+        ["photos", "reassign", from, to] => {
+            reassign_photos(&repositories, parse_id(from), parse_id(to), format)
+        }
+        ["photos", "import", user_id, file] => {
+            import_photos(&repositories, parse_id(user_id), file, format)
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2)
+        }
+    };
+
+    if let Err(e) = result {
+        exit_with("command failed", e);
+    }
+}
+
+fn exit_with<E: Display>(message: &str, e: E) -> ! {
+    eprintln!("{}: {}", message, e);
+    process::exit(1)
+}
+
+fn parse_id<T: FromStr<Err = String>>(v: &str) -> T {
+    v.parse().unwrap_or_else(|e| exit_with("invalid id", e))
+}
+
+fn list_users(repositories: &Repositories, format: Format) -> Result<(), DaoError> {
+    let users = repositories.users.get_all_with_photos()?;
+    print_users(users.iter().map(|v| (&v.0, v.1.len())), format);
+    Ok(())
+}
+
+fn search_users(repositories: &Repositories, name: &str, format: Format) -> Result<(), DaoError> {
+    let users = repositories.users.search(name.to_string())?;
+
+    let mut rows = vec![];
+    for user in &users {
+        rows.push((user, repositories.photos.get_all_by_user(user.id)?.len()));
+    }
+    print_users(rows.into_iter(), format);
+    Ok(())
+}
+
+fn show_user(repositories: &Repositories, id: UserId, format: Format) -> Result<(), DaoError> {
+    let (user, photos) = repositories.users.get_with_photos(id)?;
+
+    match format {
+        Format::Json => print_json(json!({
+            "user": user_json(&user),
+            "photos": photos.iter().map(photo_json).collect::<Vec<_>>(),
+        })),
+        Format::Table => {
+            print_table(
+                &["ID", "NAME", "VERSION", "CREATED_AT", "UPDATED_AT"],
+                vec![vec![
+                    user.id.to_string(),
+                    user.name.clone(),
+                    user.version.to_string(),
+                    user.created_at.to_string(),
+                    user.updated_at.to_string(),
+                ]],
+            );
+            println!();
+            print_photos(&photos);
+        }
+    }
+    Ok(())
+}
+
+fn force_delete_user(
+    repositories: &Repositories,
+    id: UserId,
+    format: Format,
+) -> Result<(), DaoError> {
+    let deleted_photos = repositories.transaction(|| {
+        repositories.users.get(id)?;
+        let deleted_photos = repositories.photos.delete_all_by_user(id)?;
+        repositories.users.delete(id)?;
+        Ok(deleted_photos)
+    })?;
+
+    match format {
+        Format::Json => {
+            print_json(json!({ "userId": id.to_string(), "deletedPhotos": deleted_photos }))
+        }
+        Format::Table => println!("deleted user {} and {} photos", id, deleted_photos),
+    }
+    Ok(())
+}
+
+fn toggle_visibility(
+    repositories: &Repositories,
+    id: PhotoId,
+    format: Format,
+) -> Result<(), DaoError> {
+    let now: DateTime<Utc> = Utc::now();
+
+    let photo = repositories.transaction(|| {
+        let mut photo = repositories.photos.get(id)?;
+        photo.update_visibility(!photo.is_public, now);
+        repositories.photos.update(&photo)?;
+        Ok(photo)
+    })?;
+
+    match format {
+        Format::Json => print_json(photo_json(&photo)),
+        Format::Table => print_photos(&[photo]),
+    }
+    Ok(())
+}
+
+fn reassign_photos(
+    repositories: &Repositories,
+    from: UserId,
+    to: UserId,
+    format: Format,
+) -> Result<(), DaoError> {
+    let now: DateTime<Utc> = Utc::now();
+
+    let moved = repositories.transaction(|| {
+        repositories.users.get(from)?;
+        repositories.users.get(to)?;
+        repositories.photos.reassign(from, to, now.naive_utc())
+    })?;
+
+    match format {
+        Format::Json => print_json(json!({
+            "fromUserId": from.to_string(),
+            "toUserId": to.to_string(),
+            "moved": moved,
+        })),
+        Format::Table => println!("moved {} photos from {} to {}", moved, from, to),
+    }
+    Ok(())
+}
+
+fn import_photos(
+    repositories: &Repositories,
+    user_id: UserId,
+    file: &str,
+    format: Format,
+) -> Result<(), DaoError> {
+    let rows = read_manifest(Path::new(file)).map_err(DaoError::Internal)?;
+    let results = import::import_photos(
+        repositories,
+        user_id,
+        rows,
+        &TimeOrderedIdGenerator,
+        &SystemClock,
+    )?;
+
+    match format {
+        Format::Json => print_json(json!(results
+            .iter()
+            .map(|v| match &v.result {
+                Ok(photo) => json!({ "index": v.index, "photoId": photo.id.to_string() }),
+                Err(e) => json!({ "index": v.index, "error": e }),
+            })
+            .collect::<Vec<_>>())),
+        Format::Table => {
+            print_table(
+                &["INDEX", "PHOTO_ID", "ERROR"],
+                results
+                    .iter()
+                    .map(|v| match &v.result {
+                        Ok(photo) => vec![v.index.to_string(), photo.id.to_string(), String::new()],
+                        Err(e) => vec![v.index.to_string(), String::new(), e.clone()],
+                    })
+                    .collect(),
+            );
+            let imported = results.iter().filter(|v| v.result.is_ok()).count();
+            println!();
+            println!(
+                "imported {} photos, {} rows failed",
+                imported,
+                results.len() - imported
+            );
+        }
+    }
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Result<Vec<ImportRow>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    match path.extension().and_then(|v| v.to_str()) {
+        Some("json") => serde_json::from_reader(file).map_err(|e| e.to_string()),
+        Some("csv") => csv::Reader::from_reader(file)
+            .deserialize()
+            .collect::<Result<Vec<ImportRow>, _>>()
+            .map_err(|e| e.to_string()),
+        _ => Err("the manifest must be a .json or .csv file".to_string()),
+    }
+}
+
+fn print_users<'a, I>(users: I, format: Format)
+where
+    I: Iterator<Item = (&'a domain::user::User, usize)>,
+{
+    let users = users.collect::<Vec<_>>();
+    match format {
+        Format::Json => print_json(json!(users
+            .iter()
+            .map(|(user, photos)| {
+                let mut v = user_json(user);
+                v["photos"] = json!(photos);
+                v
+            })
+            .collect::<Vec<_>>())),
+        Format::Table => print_table(
+            &["ID", "NAME", "PHOTOS", "CREATED_AT"],
+            users
+                .iter()
+                .map(|(user, photos)| {
+                    vec![
+                        user.id.to_string(),
+                        user.name.clone(),
+                        photos.to_string(),
+                        user.created_at.to_string(),
+                    ]
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn print_photos(photos: &[domain::photo::Photo]) {
+    print_table(
+        &["ID", "USER_ID", "URL", "PUBLIC", "VERSION", "CREATED_AT"],
+        photos
+            .iter()
+            .map(|v| {
+                vec![
+                    v.id.to_string(),
+                    v.user_id.to_string(),
+                    v.url.clone(),
+                    v.is_public.to_string(),
+                    v.version.to_string(),
+                    v.created_at.to_string(),
+                ]
+            })
+            .collect(),
+    );
+}
+
+fn user_json(user: &domain::user::User) -> serde_json::Value {
+    json!({
+        "id": user.id.to_string(),
+        "name": user.name,
+        "version": user.version,
+        "createdAt": user.created_at.to_string(),
+        "updatedAt": user.updated_at.to_string(),
+    })
+}
+
+fn photo_json(photo: &domain::photo::Photo) -> serde_json::Value {
+    json!({
+        "id": photo.id.to_string(),
+        "userId": photo.user_id.to_string(),
+        "url": photo.url,
+        "isPublic": photo.is_public,
+        "version": photo.version,
+        "createdAt": photo.created_at.to_string(),
+        "updatedAt": photo.updated_at.to_string(),
+    })
+}
+
+fn print_json(v: serde_json::Value) {
+    println!("{}", serde_json::to_string_pretty(&v).unwrap());
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths = headers.iter().map(|v| v.len()).collect::<Vec<_>>();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", line(headers.to_vec()));
+    for row in &rows {
+        println!("{}", line(row.iter().map(String::as_str).collect()));
+    }
+}
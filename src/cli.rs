@@ -0,0 +1,89 @@
+use crate::ddb;
+use crate::ddb::migrations;
+use crate::seed::{self, SeedOptions};
+use chrono::Utc;
+use std::io;
+
+pub const USAGE: &str =
+    "usage: rust-graphql-diesel-sample [serve | migrate up | migrate down | migrate status | \
+     seed [--seed N] [--users N] [--photos-per-user N] [--public-ratio 0..1]]";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Serve,
+    Migrate(MigrateCommand),
+    Seed(SeedOptions),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MigrateCommand {
+    Up,
+    Down,
+    Status,
+}
+
+impl Command {
+    /// Parses the arguments after the program name, no arguments means `serve`.
+    pub fn parse(args: &[String]) -> Option<Self> {
+        let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+        match args.as_slice() {
+            [] | ["serve"] => Some(Command::Serve),
+            ["migrate", "up"] => Some(Command::Migrate(MigrateCommand::Up)),
+            ["migrate", "down"] => Some(Command::Migrate(MigrateCommand::Down)),
+            ["migrate", "status"] => Some(Command::Migrate(MigrateCommand::Status)),
+            ["seed", flags @ ..] => parse_seed_options(flags).map(Command::Seed),
+            _ => None,
+        }
+    }
+}
+
+/// `--name value` pairs, anything not in `SeedOptions` is rejected.
+fn parse_seed_options(flags: &[&str]) -> Option<SeedOptions> {
+    let mut options = SeedOptions::default();
+    for pair in flags.chunks(2) {
+        match pair {
+            ["--seed", v] => options.seed = v.parse().ok()?,
+            ["--users", v] => options.users = v.parse().ok()?,
+            ["--photos-per-user", v] => options.photos_per_user = v.parse().ok()?,
+            ["--public-ratio", v] => options.public_ratio = v.parse().ok()?,
+            _ => return None,
+        }
+    }
+    if !(0.0..=1.0).contains(&options.public_ratio) {
+        return None;
+    }
+    Some(options)
+}
+
+pub fn migrate(pool: &ddb::Pool, command: MigrateCommand) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+
+    match command {
+        MigrateCommand::Up => {
+            migrations::run_pending(&conn, &mut io::stdout()).map_err(|e| e.to_string())?;
+        }
+        MigrateCommand::Down => {
+            match migrations::revert_latest(&conn).map_err(|e| e.to_string())? {
+                Some(name) => println!("Rolled back migration {}", name),
+                None => println!("No migrations to roll back"),
+            }
+        }
+        MigrateCommand::Status => {
+            for v in migrations::status(&conn).map_err(|e| e.to_string())? {
+                println!("[{}] {}", if v.applied { "X" } else { " " }, v.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn seed(pool: &ddb::Pool, options: SeedOptions) -> Result<(), String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+
+    let seeded = seed::seed(&conn, options, Utc::now()).map_err(|e| e.to_string())?;
+    println!(
+        "Seeded {} users and {} photos (seed {})",
+        seeded.users, seeded.photos, options.seed
+    );
+    Ok(())
+}
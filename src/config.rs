@@ -0,0 +1,267 @@
+use crate::cors::CorsConfig;
+use crate::domain::id::UserId;
+use crate::rate_limit::Budget;
+use serde::{Deserialize, Deserializer};
+use std::env;
+use thiserror::Error;
+
+const DEFAULTS: &str = include_str!("../config/default.toml");
+const DEFAULT_FILE: &str = "config/local.toml";
+const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("failed to load configuration: {0}")]
+    Load(#[from] ::config::ConfigError),
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub limits: LimitsConfig,
+    pub logging: LoggingConfig,
+    pub metrics: MetricsConfig,
+    pub health: HealthConfig,
+    pub exports: ExportsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub workers: usize,
+}
+
+impl ServerConfig {
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_size: u32,
+    pub connection_timeout_secs: u64,
+    pub run_migrations_on_startup: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthConfig {
+    pub user_id_header: String,
+    /// Users allowed to run admin-only fields such as `Query.auditEvents`.
+    #[serde(deserialize_with = "list_or_csv")]
+    pub admin_user_ids: Vec<String>,
+}
+
+impl AuthConfig {
+    pub fn is_admin(&self, user_id: UserId) -> bool {
+        self.admin_user_ids
+            .iter()
+            .any(|v| v.parse::<UserId>() == Ok(user_id))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LimitsConfig {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+    pub max_aliases: usize,
+    pub persisted_queries: PersistedQueriesConfig,
+    pub rate_limit: RateLimitConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PersistedQueriesConfig {
+    pub cache_size: usize,
+    pub allowlist_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitConfig {
+    pub query: Budget,
+    pub mutation: Budget,
+    pub trust_forwarded_for: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggingConfig {
+    pub level: String,
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthConfig {
+    pub timeout_ms: u64,
+    /// Share of the pool in use at which the instance reports itself as not ready.
+    pub max_pool_utilisation: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportsConfig {
+    pub dir: String,
+    pub retention_hours: i64,
+    pub stale_after_minutes: i64,
+    pub sweep_interval_minutes: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+impl Config {
+    /// Layers `config/default.toml`, the optional file named by `APP_CONFIG` and `APP_*` variables.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut c = ::config::Config::default();
+        c.merge(::config::File::from_str(
+            DEFAULTS,
+            ::config::FileFormat::Toml,
+        ))?;
+
+        let path = env::var("APP_CONFIG").unwrap_or_else(|_| DEFAULT_FILE.to_string());
+        c.merge(::config::File::with_name(&path).required(false))?;
+        c.merge(::config::Environment::with_prefix("APP").separator("__"))?;
+
+        // variables that predate the config file
+        if let Ok(v) = env::var("PORT") {
+            c.set("server.port", v)?;
+        }
+        if let Ok(v) = env::var("DATABASE_URL") {
+            c.set("database.url", v)?;
+        }
+
+        let config: Config = c.try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Only `config/default.toml`, unvalidated, for callers that fill in the rest themselves.
+    pub fn defaults() -> Result<Self, ConfigError> {
+        let mut c = ::config::Config::default();
+        c.merge(::config::File::from_str(
+            DEFAULTS,
+            ::config::FileFormat::Toml,
+        ))?;
+        Ok(c.try_into()?)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.port == 0 {
+            return Err(ConfigError::Invalid(
+                "server.port must not be 0".to_string(),
+            ));
+        }
+        if self.database.url.is_empty() {
+            return Err(ConfigError::Invalid(
+                "database.url must be set, e.g. with DATABASE_URL".to_string(),
+            ));
+        }
+        if self.database.pool_size == 0 {
+            return Err(ConfigError::Invalid(
+                "database.pool_size must be at least 1".to_string(),
+            ));
+        }
+        if self.auth.user_id_header.is_empty() {
+            return Err(ConfigError::Invalid(
+                "auth.user_id_header must not be empty".to_string(),
+            ));
+        }
+        if let Some(v) = self
+            .auth
+            .admin_user_ids
+            .iter()
+            .find(|v| v.parse::<UserId>().is_err())
+        {
+            return Err(ConfigError::Invalid(format!(
+                "auth.admin_user_ids must be UUIDs, got {}",
+                v
+            )));
+        }
+        // browsers refuse credentialed responses that allow any origin
+        if self.cors.allow_credentials && self.cors.allowed_origins.iter().any(|v| v == "*") {
+            return Err(ConfigError::Invalid(
+                "cors.allowed_origins must list origins when cors.allow_credentials is set, not \"*\""
+                    .to_string(),
+            ));
+        }
+        if self.limits.max_depth == 0 || self.limits.max_complexity == 0 {
+            return Err(ConfigError::Invalid(
+                "limits.max_depth and limits.max_complexity must be at least 1".to_string(),
+            ));
+        }
+        if self.limits.persisted_queries.cache_size == 0 {
+            return Err(ConfigError::Invalid(
+                "limits.persisted_queries.cache_size must be at least 1".to_string(),
+            ));
+        }
+        if self.limits.rate_limit.query.burst == 0 || self.limits.rate_limit.mutation.burst == 0 {
+            return Err(ConfigError::Invalid(
+                "limits.rate_limit burst must be at least 1".to_string(),
+            ));
+        }
+        if self.health.timeout_ms == 0 {
+            return Err(ConfigError::Invalid(
+                "health.timeout_ms must be at least 1".to_string(),
+            ));
+        }
+        if !(self.health.max_pool_utilisation > 0.0 && self.health.max_pool_utilisation <= 1.0) {
+            return Err(ConfigError::Invalid(
+                "health.max_pool_utilisation must be in (0, 1]".to_string(),
+            ));
+        }
+        if self.exports.dir.is_empty() || self.exports.retention_hours <= 0 {
+            return Err(ConfigError::Invalid(
+                "exports.dir must be set and exports.retention_hours must be at least 1"
+                    .to_string(),
+            ));
+        }
+        if self.exports.stale_after_minutes <= 0 || self.exports.sweep_interval_minutes == 0 {
+            return Err(ConfigError::Invalid(
+                "exports.stale_after_minutes and exports.sweep_interval_minutes must be at least 1"
+                    .to_string(),
+            ));
+        }
+        if !LOG_LEVELS.contains(&self.logging.level.as_str()) {
+            return Err(ConfigError::Invalid(format!(
+                "logging.level must be one of {}",
+                LOG_LEVELS.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Accepts a TOML array or, for environment variables, a comma separated string.
+pub fn list_or_csv<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrCsv {
+        List(Vec<String>),
+        Csv(String),
+    }
+
+    Ok(match ListOrCsv::deserialize(deserializer)? {
+        ListOrCsv::List(v) => v,
+        ListOrCsv::Csv(v) => v
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect(),
+    })
+}
@@ -0,0 +1,40 @@
+use crate::telemetry::REQUEST_ID_HEADER;
+use actix_cors::Cors;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CorsConfig {
+    #[serde(deserialize_with = "crate::config::list_or_csv")]
+    pub allowed_origins: Vec<String>,
+    pub allow_credentials: bool,
+    #[serde(deserialize_with = "crate::config::list_or_csv")]
+    pub allowed_headers: Vec<String>,
+    pub max_age: usize,
+}
+
+impl CorsConfig {
+    /// Builds the middleware, an empty origin list rejects every cross-origin request and `*` allows any origin.
+    /// `user_id_header` is allowed on top of `allowed_headers`, so renaming it can't break preflights.
+    pub fn build(&self, user_id_header: &str) -> Cors {
+        let mut cors = Cors::default()
+            .allowed_methods(vec!["GET", "POST"])
+            .allowed_headers(self.allowed_headers.iter().map(String::as_str))
+            .allowed_header(user_id_header)
+            .expose_headers(vec![REQUEST_ID_HEADER])
+            .max_age(self.max_age);
+
+        for origin in &self.allowed_origins {
+            cors = if origin == "*" {
+                cors.allow_any_origin()
+            } else {
+                cors.allowed_origin(origin)
+            };
+        }
+
+        if self.allow_credentials {
+            cors = cors.supports_credentials();
+        }
+
+        cors
+    }
+}
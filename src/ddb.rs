@@ -1,20 +1,177 @@
-use std::env;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
+use crate::config::DatabaseConfig;
+use crate::metrics;
 use diesel::connection::TransactionManager;
-use diesel::mysql::MysqlConnection;
 use diesel::prelude::*;
+use diesel::query_builder::{QueryBuilder, QueryFragment};
+use diesel::r2d2::{self, ConnectionManager};
 use std::future::Future;
 use thiserror::Error;
+use tracing::{debug, field, info_span};
 
+pub mod audit_event;
+pub mod data_export;
+pub mod migrations;
 pub mod photo;
+#[cfg(feature = "mysql")]
+mod schema;
+#[cfg(feature = "sqlite")]
+#[path = "ddb/schema_sqlite.rs"]
+mod schema;
+#[cfg(feature = "postgres")]
+#[path = "ddb/schema_postgres.rs"]
 mod schema;
 pub mod user;
 
-pub fn establish_connection() -> MysqlConnection {
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    MysqlConnection::establish(&database_url)
-        .expect(&format!("Error connecting to {}", database_url))
+#[cfg(any(
+    all(feature = "mysql", feature = "sqlite"),
+    all(feature = "mysql", feature = "postgres"),
+    all(feature = "sqlite", feature = "postgres")
+))]
+compile_error!(
+    "features `mysql`, `sqlite` and `postgres` are mutually exclusive, build with `--no-default-features`"
+);
+#[cfg(not(any(feature = "mysql", feature = "sqlite", feature = "postgres")))]
+compile_error!("one database backend feature has to be enabled: `mysql`, `sqlite` or `postgres`");
+
+#[cfg(feature = "mysql")]
+pub type Backend = diesel::mysql::Mysql;
+#[cfg(feature = "mysql")]
+pub type DbConnection = diesel::mysql::MysqlConnection;
+#[cfg(feature = "mysql")]
+type BackendQueryBuilder = diesel::mysql::MysqlQueryBuilder;
+
+#[cfg(feature = "sqlite")]
+pub type Backend = diesel::sqlite::Sqlite;
+#[cfg(feature = "sqlite")]
+pub type DbConnection = diesel::sqlite::SqliteConnection;
+#[cfg(feature = "sqlite")]
+type BackendQueryBuilder = diesel::sqlite::SqliteQueryBuilder;
+
+#[cfg(feature = "postgres")]
+pub type Backend = diesel::pg::Pg;
+#[cfg(feature = "postgres")]
+pub type DbConnection = diesel::pg::PgConnection;
+#[cfg(feature = "postgres")]
+type BackendQueryBuilder = diesel::pg::PgQueryBuilder;
+
+pub type Pool = r2d2::Pool<ConnectionManager<DbConnection>>;
+pub type Connection = r2d2::PooledConnection<ConnectionManager<DbConnection>>;
+
+/// Run on every new connection. Timestamps are written as UTC `DATETIME`/`TIMESTAMP` values
+/// without a zone, so MySQL and Postgres sessions are pinned to UTC as well and `NOW()` or any
+/// server-side conversion agrees with them whatever the server's default zone is.
+#[cfg(feature = "mysql")]
+const SESSION_SETUP: &str = "SET time_zone = '+00:00';";
+/// SQLite only enforces foreign keys when asked to, and fails instead of waiting on a locked database.
+#[cfg(feature = "sqlite")]
+const SESSION_SETUP: &str = "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;";
+#[cfg(feature = "postgres")]
+const SESSION_SETUP: &str = "SET TIME ZONE 'UTC';";
+
+pub fn new_pool(config: &DatabaseConfig) -> Result<Pool, r2d2::PoolError> {
+    r2d2::Pool::builder()
+        .max_size(config.pool_size)
+        .connection_timeout(Duration::from_secs(config.connection_timeout_secs))
+        .connection_customizer(Box::new(SessionSetup))
+        .build(ConnectionManager::new(config.url.as_str()))
+}
+
+/// Applies `SESSION_SETUP` to a connection that doesn't come from `new_pool`.
+pub fn setup_session(conn: &DbConnection) -> QueryResult<()> {
+    use diesel::connection::SimpleConnection;
+
+    conn.batch_execute(SESSION_SETUP)
+}
+
+#[derive(Debug)]
+struct SessionSetup;
+
+impl r2d2::CustomizeConnection<DbConnection, r2d2::Error> for SessionSetup {
+    fn on_acquire(&self, conn: &mut DbConnection) -> Result<(), r2d2::Error> {
+        setup_session(conn).map_err(r2d2::Error::QueryError)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Sort order for a listing, `F` is a per-table enum so only indexed columns can be chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderBy<F> {
+    pub field: F,
+    pub direction: SortDirection,
+}
+
+/// `LIKE` pattern matching `v` anywhere, with `%` and `_` in `v` matched literally.
+///
+/// Use it with `.escape('\\')`, SQLite has no default escape character.
+pub(crate) fn contains_pattern(v: &str) -> String {
+    format!(
+        "%{}%",
+        v.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
+
+pub fn ping(conn: &DbConnection) -> QueryResult<()> {
+    diesel::sql_query("SELECT 1").execute(conn).map(|_| ())
+}
+
+/// Runs `query` inside a `dao_query` span recording the statement without binds, row count and duration.
+pub(crate) fn traced<Q, R, F>(method: &'static str, query: Q, f: F) -> QueryResult<R>
+where
+    Q: QueryFragment<Backend>,
+    R: Rows,
+    F: FnOnce(Q) -> QueryResult<R>,
+{
+    let mut statement = BackendQueryBuilder::new();
+    if query.to_sql(&mut statement).is_err() {
+        statement = BackendQueryBuilder::new();
+    }
+
+    let span = info_span!(
+        "dao_query",
+        method,
+        statement = %statement.finish(),
+        rows = field::Empty,
+        duration_ms = field::Empty
+    );
+    let _enter = span.enter();
+
+    let started_at = Instant::now();
+    let result = f(query);
+    let elapsed = started_at.elapsed();
+    metrics::observe_dao_query(method, elapsed);
+    span.record("duration_ms", &(elapsed.as_millis() as u64));
+    if let Ok(v) = &result {
+        span.record("rows", &(v.rows() as u64));
+    }
+    debug!("query executed");
+
+    result
+}
+
+pub(crate) trait Rows {
+    fn rows(&self) -> usize;
+}
+
+impl<T> Rows for Vec<T> {
+    fn rows(&self) -> usize {
+        self.len()
+    }
+}
+
+impl Rows for usize {
+    fn rows(&self) -> usize {
+        *self
+    }
 }
 
 pub struct Dao<T> {
@@ -32,14 +189,14 @@ impl<T> Dao<T> {
 pub struct Tx {}
 
 impl Tx {
-    pub fn run<R, F>(conn: &MysqlConnection, f: F) -> DaoResult<R>
+    pub fn run<R, F>(conn: &DbConnection, f: F) -> DaoResult<R>
     where
         F: FnOnce() -> DaoResult<R>,
     {
         conn.transaction(|| f())
     }
 
-    pub async fn run_async<R, F>(conn: &MysqlConnection, f: F) -> DaoResult<R>
+    pub async fn run_async<R, F>(conn: &DbConnection, f: F) -> DaoResult<R>
     where
         F: Future<Output = DaoResult<R>>,
     {
@@ -64,6 +221,8 @@ pub enum DaoError {
     NotFound,
     #[error("forbidden")]
     Forbidden,
+    #[error("conflict")]
+    Conflict,
     #[error("internal error: {0}")]
     Internal(String),
 }
@@ -0,0 +1,200 @@
+use crate::ddb;
+use crate::ddb::schema::audit_events;
+use crate::ddb::{Dao, DaoError, DaoResult, DbConnection};
+use crate::domain;
+use crate::domain::audit_event::FieldChange;
+use crate::domain::id::{AuditEventId, UserId};
+use diesel::prelude::*;
+use std::convert::TryFrom;
+
+/// Largest page `Query.auditEvents` serves, also its page size when none is asked for.
+pub const FIND_LIMIT: usize = 500;
+
+#[derive(Queryable, Insertable, Debug, Clone, Eq, PartialEq, Identifiable)]
+#[table_name = "audit_events"]
+pub struct Entity {
+    pub id: AuditEventId,
+    pub actor_id: UserId,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub changes: String,
+    pub request_id: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl ddb::Rows for Entity {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
+impl TryFrom<Entity> for domain::audit_event::AuditEvent {
+    type Error = String;
+
+    fn try_from(e: Entity) -> Result<Self, Self::Error> {
+        Ok(domain::audit_event::AuditEvent {
+            id: e.id,
+            actor_id: e.actor_id,
+            action: e.action,
+            entity_type: e.entity_type,
+            entity_id: e.entity_id,
+            changes: FieldChange::decode(&e.changes)?,
+            request_id: e.request_id,
+            created_at: e.created_at,
+        })
+    }
+}
+
+impl From<domain::audit_event::AuditEvent> for Entity {
+    fn from(d: domain::audit_event::AuditEvent) -> Entity {
+        Entity {
+            id: d.id,
+            actor_id: d.actor_id,
+            action: d.action,
+            entity_type: d.entity_type,
+            entity_id: d.entity_id,
+            changes: FieldChange::encode(&d.changes),
+            request_id: d.request_id,
+            created_at: d.created_at,
+        }
+    }
+}
+
+/// Every set field has to match exactly, `entity_id` is only useful together with `entity_type`.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub actor_id: Option<UserId>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub request_id: Option<String>,
+    pub created_after: Option<chrono::NaiveDateTime>,
+    pub created_before: Option<chrono::NaiveDateTime>,
+}
+
+/// Position of an event in the newest first order, a page continues with the events after it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cursor {
+    pub created_at: chrono::NaiveDateTime,
+    pub id: AuditEventId,
+}
+
+impl Cursor {
+    pub fn of(event: &domain::audit_event::AuditEvent) -> Self {
+        Cursor {
+            created_at: event.created_at,
+            id: event.id,
+        }
+    }
+
+    /// `<seconds>.<microseconds>_<id>`, opaque to clients.
+    pub fn encode(&self) -> String {
+        format!(
+            "{}.{:06}_{}",
+            self.created_at.timestamp(),
+            self.created_at.timestamp_subsec_micros(),
+            self.id
+        )
+    }
+
+    pub fn decode(v: &str) -> Option<Self> {
+        let mut parts = v.splitn(2, '_');
+        let mut timestamp = parts.next()?.splitn(2, '.');
+        let seconds = timestamp.next()?.parse::<i64>().ok()?;
+        let micros = timestamp.next()?.parse::<u32>().ok()?;
+        let id = parts.next()?.parse::<AuditEventId>().ok()?;
+
+        Some(Cursor {
+            created_at: chrono::NaiveDateTime::from_timestamp_opt(
+                seconds,
+                micros.checked_mul(1000)?,
+            )?,
+            id,
+        })
+    }
+}
+
+impl Dao<domain::audit_event::AuditEvent> {
+    /// Newest first, at most `limit` events and only those after `after`.
+    pub fn find(
+        &self,
+        conn: &DbConnection,
+        filter: &Filter,
+        after: Option<&Cursor>,
+        limit: usize,
+    ) -> DaoResult<Vec<domain::audit_event::AuditEvent>> {
+        let mut query = audit_events::table.into_boxed::<ddb::Backend>();
+        if let Some(v) = filter.actor_id {
+            query = query.filter(audit_events::actor_id.eq(v));
+        }
+        if let Some(v) = &filter.action {
+            query = query.filter(audit_events::action.eq(v.clone()));
+        }
+        if let Some(v) = &filter.entity_type {
+            query = query.filter(audit_events::entity_type.eq(v.clone()));
+        }
+        if let Some(v) = &filter.entity_id {
+            query = query.filter(audit_events::entity_id.eq(v.clone()));
+        }
+        if let Some(v) = &filter.request_id {
+            query = query.filter(audit_events::request_id.eq(v.clone()));
+        }
+        if let Some(v) = filter.created_after {
+            query = query.filter(audit_events::created_at.gt(v));
+        }
+        if let Some(v) = filter.created_before {
+            query = query.filter(audit_events::created_at.lt(v));
+        }
+        if let Some(v) = after {
+            query = query.filter(
+                audit_events::created_at
+                    .lt(v.created_at)
+                    .or(audit_events::created_at
+                        .eq(v.created_at)
+                        .and(audit_events::id.lt(v.id))),
+            );
+        }
+
+        ddb::traced(
+            "audit_event.find",
+            query
+                .order(audit_events::created_at.desc())
+                .then_order_by(audit_events::id.desc())
+                .limit(limit as i64),
+            |q| q.load::<Entity>(conn),
+        )
+        .map_err(DaoError::from)?
+        .into_iter()
+        .map(|v| domain::audit_event::AuditEvent::try_from(v).map_err(DaoError::from))
+        .collect()
+    }
+
+    /// Inserts `items` with a single multi-row statement, or one statement per row on SQLite.
+    pub fn insert_all(
+        &self,
+        conn: &DbConnection,
+        items: &[domain::audit_event::AuditEvent],
+    ) -> DaoResult<usize> {
+        let entities = items.iter().cloned().map(Entity::from).collect::<Vec<_>>();
+        #[cfg(not(feature = "sqlite"))]
+        let inserted = ddb::traced(
+            "audit_event.insert_all",
+            diesel::insert_into(audit_events::table).values(entities),
+            |q| q.execute(conn),
+        );
+
+        // same as photos, the caller's transaction keeps the rows together on SQLite
+        #[cfg(feature = "sqlite")]
+        let inserted = entities.into_iter().try_fold(0, |inserted, e| {
+            ddb::traced(
+                "audit_event.insert_all",
+                diesel::insert_into(audit_events::table).values(e),
+                |q| q.execute(conn),
+            )
+            .map(|v| inserted + v)
+        });
+
+        inserted.map_err(DaoError::from)
+    }
+}
@@ -0,0 +1,171 @@
+use crate::ddb;
+use crate::ddb::schema::data_exports;
+use crate::ddb::user;
+use crate::ddb::{Dao, DaoError, DaoResult, DbConnection};
+use crate::domain;
+use crate::domain::data_export::DataExportStatus;
+use crate::domain::id::UserId;
+use diesel::prelude::*;
+use std::convert::TryFrom;
+
+#[derive(Queryable, Insertable, Debug, Clone, Eq, PartialEq, Identifiable, Associations)]
+#[belongs_to(user::Entity, foreign_key = "user_id")]
+#[table_name = "data_exports"]
+pub struct Entity {
+    pub id: String,
+    pub user_id: UserId,
+    pub status: String,
+    pub format_version: i32,
+    pub file_name: Option<String>,
+    pub error: Option<String>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl ddb::Rows for Entity {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
+impl TryFrom<Entity> for domain::data_export::DataExport {
+    type Error = String;
+
+    fn try_from(e: Entity) -> Result<Self, Self::Error> {
+        Ok(domain::data_export::DataExport {
+            id: e.id,
+            user_id: e.user_id,
+            status: DataExportStatus::parse(&e.status)?,
+            format_version: e.format_version,
+            file_name: e.file_name,
+            error: e.error,
+            expires_at: e.expires_at,
+            created_at: e.created_at,
+            updated_at: e.updated_at,
+        })
+    }
+}
+
+impl From<domain::data_export::DataExport> for Entity {
+    fn from(d: domain::data_export::DataExport) -> Entity {
+        Entity {
+            id: d.id,
+            user_id: d.user_id,
+            status: d.status.as_str().to_string(),
+            format_version: d.format_version,
+            file_name: d.file_name,
+            error: d.error,
+            expires_at: d.expires_at,
+            created_at: d.created_at,
+            updated_at: d.updated_at,
+        }
+    }
+}
+
+impl Dao<domain::data_export::DataExport> {
+    pub fn get(
+        &self,
+        conn: &DbConnection,
+        id: String,
+    ) -> DaoResult<domain::data_export::DataExport> {
+        let e = ddb::traced("data_export.get", data_exports::table.find(id), |q| {
+            q.first::<Entity>(conn)
+        })
+        .map_err(DaoError::from)?;
+        Ok(domain::data_export::DataExport::try_from(e)?)
+    }
+
+    pub fn get_all_by_user(
+        &self,
+        conn: &DbConnection,
+        user_id: UserId,
+    ) -> DaoResult<Vec<domain::data_export::DataExport>> {
+        ddb::traced(
+            "data_export.get_all_by_user",
+            data_exports::table
+                .filter(data_exports::user_id.eq(user_id))
+                .order(data_exports::created_at.desc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map_err(DaoError::from)?
+        .into_iter()
+        .map(|v| domain::data_export::DataExport::try_from(v).map_err(DaoError::from))
+        .collect()
+    }
+
+    /// Pending or running exports last updated before `updated_before`.
+    pub fn get_stale(
+        &self,
+        conn: &DbConnection,
+        updated_before: chrono::NaiveDateTime,
+    ) -> DaoResult<Vec<domain::data_export::DataExport>> {
+        let active = vec![
+            DataExportStatus::Pending.as_str(),
+            DataExportStatus::Running.as_str(),
+        ];
+        ddb::traced(
+            "data_export.get_stale",
+            data_exports::table
+                .filter(data_exports::status.eq_any(active))
+                .filter(data_exports::updated_at.lt(updated_before)),
+            |q| q.load::<Entity>(conn),
+        )
+        .map_err(DaoError::from)?
+        .into_iter()
+        .map(|v| domain::data_export::DataExport::try_from(v).map_err(DaoError::from))
+        .collect()
+    }
+
+    /// Completed exports that expired at or before `now` and still have an archive.
+    pub fn get_expired(
+        &self,
+        conn: &DbConnection,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<Vec<domain::data_export::DataExport>> {
+        ddb::traced(
+            "data_export.get_expired",
+            data_exports::table
+                .filter(data_exports::status.eq(DataExportStatus::Completed.as_str()))
+                .filter(data_exports::expires_at.le(now))
+                .filter(data_exports::file_name.is_not_null()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map_err(DaoError::from)?
+        .into_iter()
+        .map(|v| domain::data_export::DataExport::try_from(v).map_err(DaoError::from))
+        .collect()
+    }
+
+    pub fn insert(
+        &self,
+        conn: &DbConnection,
+        item: &domain::data_export::DataExport,
+    ) -> DaoResult<()> {
+        let e: Entity = item.clone().into();
+        ddb::traced(
+            "data_export.insert",
+            diesel::insert_into(data_exports::table).values(e),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)?;
+        Ok(())
+    }
+
+    pub fn update(
+        &self,
+        conn: &DbConnection,
+        item: &domain::data_export::DataExport,
+    ) -> DaoResult<()> {
+        let e: Entity = item.clone().into();
+        let query = diesel::update(data_exports::table.find(e.id)).set((
+            data_exports::status.eq(e.status),
+            data_exports::file_name.eq(e.file_name),
+            data_exports::error.eq(e.error),
+            data_exports::expires_at.eq(e.expires_at),
+            data_exports::updated_at.eq(e.updated_at),
+        ));
+        ddb::traced("data_export.update", query, |q| q.execute(conn)).map_err(DaoError::from)?;
+        Ok(())
+    }
+}
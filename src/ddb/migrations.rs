@@ -0,0 +1,167 @@
+use crate::ddb::DbConnection;
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel_migrations::{Migration, MigrationConnection, MigrationError, RunMigrationsError};
+use std::io;
+
+/// Directory of the migration set for the enabled backend, relative to this file.
+#[cfg(feature = "mysql")]
+macro_rules! migrations_dir {
+    () => {
+        "../../migrations/"
+    };
+}
+#[cfg(feature = "sqlite")]
+macro_rules! migrations_dir {
+    () => {
+        "../../migrations_sqlite/"
+    };
+}
+#[cfg(feature = "postgres")]
+macro_rules! migrations_dir {
+    () => {
+        "../../migrations_postgres/"
+    };
+}
+
+#[cfg(not(feature = "postgres"))]
+const DELETE_MIGRATION_VERSION: &str = "DELETE FROM __diesel_schema_migrations WHERE version = ?";
+#[cfg(feature = "postgres")]
+const DELETE_MIGRATION_VERSION: &str = "DELETE FROM __diesel_schema_migrations WHERE version = $1";
+
+/// Embeds `<migrations_dir>/<dir>/{up,down}.sql`, every new migration directory has to exist in
+/// each backend's set and be listed in `all`, which `tests/migrations.rs` checks.
+///
+/// `embed_migrations!` can't be used, its migrations can only be run and never reverted.
+macro_rules! migration {
+    ($dir:literal) => {
+        EmbeddedMigration::new(
+            $dir,
+            include_str!(concat!(migrations_dir!(), $dir, "/up.sql")),
+            include_str!(concat!(migrations_dir!(), $dir, "/down.sql")),
+        )
+    };
+}
+
+fn all() -> Vec<EmbeddedMigration> {
+    vec![
+        migration!("2021-07-02-142441_create_users"),
+        migration!("2021-07-04-133921_create_photos"),
+        migration!("2021-07-10-120000_add_version_to_users_and_photos"),
+        migration!("2021-07-20-120000_create_data_exports"),
+        migration!("2021-07-25-120000_add_listing_indexes"),
+        migration!("2021-08-01-120000_binary_ids"),
+        migration!("2021-08-10-120000_create_audit_events"),
+    ]
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddedMigration {
+    pub name: &'static str,
+    version: String,
+    up: &'static str,
+    down: &'static str,
+}
+
+impl EmbeddedMigration {
+    fn new(name: &'static str, up: &'static str, down: &'static str) -> Self {
+        // same rule as the diesel CLI, `2021-07-02-142441_create_users` is version `20210702142441`
+        let version = name.split('_').next().unwrap_or_default().replace('-', "");
+        EmbeddedMigration {
+            name,
+            version,
+            up,
+            down,
+        }
+    }
+}
+
+impl Migration for EmbeddedMigration {
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn run(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        conn.batch_execute(self.up)
+            .map_err(RunMigrationsError::QueryError)
+    }
+
+    fn revert(&self, conn: &dyn SimpleConnection) -> Result<(), RunMigrationsError> {
+        conn.batch_execute(self.down)
+            .map_err(RunMigrationsError::QueryError)
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub name: &'static str,
+    pub applied: bool,
+}
+
+// MySQL and Postgres turn UUID text into bytes in plain SQL, SQLite needs a helper function.
+#[cfg(feature = "sqlite")]
+sql_function!(fn uuid_to_bin(v: Text) -> diesel::sql_types::Nullable<diesel::sql_types::Binary>);
+
+/// Applies every pending migration, logging each one to `out`.
+pub fn run_pending(conn: &DbConnection, out: &mut dyn io::Write) -> Result<(), RunMigrationsError> {
+    #[cfg(feature = "sqlite")]
+    uuid_to_bin::register_impl(conn, |v: String| {
+        uuid::Uuid::parse_str(&v)
+            .ok()
+            .map(|v| v.as_bytes().to_vec())
+    })?;
+
+    diesel_migrations::run_migrations(conn, all(), out)
+}
+
+/// Reverts the most recently applied migration and returns its name, or `None` when nothing is applied.
+pub fn revert_latest(conn: &DbConnection) -> Result<Option<&'static str>, RunMigrationsError> {
+    conn.setup()?;
+    let latest = match conn.latest_run_migration_version()? {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let migration = all()
+        .into_iter()
+        .find(|v| v.version == latest)
+        .ok_or_else(|| {
+            RunMigrationsError::MigrationError(MigrationError::UnknownMigrationVersion(
+                latest.clone(),
+            ))
+        })?;
+
+    // MySQL commits every DDL statement implicitly, a transaction would only look atomic there.
+    // A failing `down.sql` can leave the migration partly reverted and still recorded as applied.
+    #[cfg(feature = "mysql")]
+    let reverted = revert(conn, &migration);
+    #[cfg(not(feature = "mysql"))]
+    let reverted = conn.transaction(|| revert(conn, &migration));
+
+    reverted.map(|_| Some(migration.name))
+}
+
+/// Runs `down.sql` and forgets that `migration` was applied.
+fn revert(conn: &DbConnection, migration: &EmbeddedMigration) -> Result<(), RunMigrationsError> {
+    migration.revert(conn)?;
+    diesel::sql_query(DELETE_MIGRATION_VERSION)
+        .bind::<Text, _>(&migration.version)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn status(conn: &DbConnection) -> Result<Vec<MigrationStatus>, RunMigrationsError> {
+    conn.setup()?;
+    let applied = conn.previously_run_migration_versions()?;
+    Ok(all()
+        .into_iter()
+        .map(|v| MigrationStatus {
+            name: v.name,
+            applied: applied.contains(&v.version),
+        })
+        .collect())
+}
+
+pub fn has_pending(conn: &DbConnection) -> Result<bool, RunMigrationsError> {
+    Ok(status(conn)?.iter().any(|v| !v.applied))
+}
@@ -1,28 +1,33 @@
-use crate::ddb::photo;
+use crate::ddb;
 use crate::ddb::schema::photos;
 use crate::ddb::schema::users;
 use crate::ddb::user;
-use crate::ddb::{Dao, DaoError, DaoResult};
+use crate::ddb::{Dao, DaoError, DaoResult, DbConnection, OrderBy, SortDirection};
 use crate::domain;
-use async_trait::async_trait;
-use dataloader::{cached, BatchFn};
+use crate::domain::id::{PhotoId, UserId};
 use diesel::prelude::*;
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::sync::{Arc, Mutex};
 
 #[derive(Queryable, Insertable, Debug, Clone, Eq, PartialEq, Identifiable, Associations)]
 #[belongs_to(user::Entity, foreign_key = "user_id")]
 #[table_name = "photos"]
 pub struct Entity {
-    pub id: String,
-    pub user_id: String,
+    pub id: PhotoId,
+    pub user_id: UserId,
     pub url: String,
     pub is_public: bool,
+    pub version: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
+impl ddb::Rows for Entity {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
 impl TryFrom<Entity> for domain::photo::Photo {
     type Error = String;
 
@@ -32,6 +37,7 @@ impl TryFrom<Entity> for domain::photo::Photo {
             user_id: e.user_id,
             url: e.url,
             is_public: e.is_public,
+            version: e.version,
             created_at: e.created_at,
             updated_at: e.updated_at,
         })
@@ -45,111 +51,327 @@ impl From<domain::photo::Photo> for Entity {
             user_id: d.user_id,
             url: d.url,
             is_public: d.is_public,
+            version: d.version,
             created_at: d.created_at,
             updated_at: d.updated_at,
         }
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub is_public: Option<bool>,
+    pub user_id: Option<UserId>,
+    pub created_after: Option<chrono::NaiveDateTime>,
+    pub created_before: Option<chrono::NaiveDateTime>,
+}
+
+/// Columns photos can be sorted by, each backed by an index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderField {
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Default for OrderBy<OrderField> {
+    fn default() -> Self {
+        OrderBy {
+            field: OrderField::CreatedAt,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
 impl Dao<domain::photo::Photo> {
-    pub fn get_all_with_user(
+    pub fn find(
         &self,
-        conn: &MysqlConnection,
+        conn: &DbConnection,
+        filter: &Filter,
+        order_by: OrderBy<OrderField>,
+    ) -> DaoResult<Vec<domain::photo::Photo>> {
+        let mut query = photos::table.into_boxed::<ddb::Backend>();
+        if let Some(v) = filter.is_public {
+            query = query.filter(photos::is_public.eq(v));
+        }
+        if let Some(v) = filter.user_id {
+            query = query.filter(photos::user_id.eq(v));
+        }
+        if let Some(v) = filter.created_after {
+            query = query.filter(photos::created_at.gt(v));
+        }
+        if let Some(v) = filter.created_before {
+            query = query.filter(photos::created_at.lt(v));
+        }
+        query = match (order_by.field, order_by.direction) {
+            (OrderField::CreatedAt, SortDirection::Asc) => query.order(photos::created_at.asc()),
+            (OrderField::CreatedAt, SortDirection::Desc) => query.order(photos::created_at.desc()),
+            (OrderField::UpdatedAt, SortDirection::Asc) => query.order(photos::updated_at.asc()),
+            (OrderField::UpdatedAt, SortDirection::Desc) => query.order(photos::updated_at.desc()),
+        };
+
+        ddb::traced("photo.find", query.then_order_by(photos::id.asc()), |q| {
+            q.load::<Entity>(conn)
+        })
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::photo::Photo::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from)
+    }
+
+    /// Same as `find`, with the owner of each photo loaded in one extra query.
+    pub fn find_with_user(
+        &self,
+        conn: &DbConnection,
+        filter: &Filter,
+        order_by: OrderBy<OrderField>,
     ) -> DaoResult<Vec<(domain::photo::Photo, domain::user::User)>> {
-        let join = photos::table.inner_join(users::table);
-
-        join.load::<(photo::Entity, user::Entity)>(conn)
-            .map(|v: Vec<(photo::Entity, user::Entity)>| {
-                v.into_iter()
-                    .map(|v| {
-                        (
-                            domain::photo::Photo::try_from(v.0).unwrap(),
-                            domain::user::User::try_from(v.1).unwrap(),
-                        )
-                    })
-                    .collect::<Vec<_>>()
+        let photos = self.find(conn, filter, order_by)?;
+
+        let mut user_ids = photos.iter().map(|v| v.user_id).collect::<Vec<_>>();
+        user_ids.sort();
+        user_ids.dedup();
+        let users = ddb::traced(
+            "photo.find_with_user",
+            users::table.filter(users::id.eq_any(user_ids)),
+            |q| q.load::<user::Entity>(conn),
+        )
+        .map_err(DaoError::from)?
+        .into_iter()
+        .map(|v| (v.id, domain::user::User::try_from(v).unwrap()))
+        .collect::<HashMap<_, _>>();
+
+        Ok(photos
+            .into_iter()
+            .filter_map(|v| {
+                let user = users.get(&v.user_id)?.clone();
+                Some((v, user))
             })
-            .map_err(DaoError::from)
+            .collect::<Vec<_>>())
     }
 
     pub fn get_all_by_user(
         &self,
-        conn: &MysqlConnection,
-        user_id: String,
+        conn: &DbConnection,
+        user_id: UserId,
     ) -> DaoResult<Vec<domain::photo::Photo>> {
-        return photos::table
-            .filter(photos::user_id.eq(user_id))
-            .order(photos::created_at.desc())
-            .load::<Entity>(conn)
-            .map(|v: Vec<Entity>| {
-                v.into_iter()
-                    .map(|v| domain::photo::Photo::try_from(v).unwrap())
-                    .collect::<Vec<_>>()
-            })
-            .map_err(DaoError::from);
+        return ddb::traced(
+            "photo.get_all_by_user",
+            photos::table
+                .filter(photos::user_id.eq(user_id))
+                .order(photos::created_at.desc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::photo::Photo::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from);
+    }
+
+    pub fn get(&self, conn: &DbConnection, id: PhotoId) -> DaoResult<domain::photo::Photo> {
+        ddb::traced("photo.get", photos::table.find(id), |q| {
+            q.first::<Entity>(conn)
+        })
+        .map(|v: Entity| domain::photo::Photo::try_from(v).unwrap())
+        .map_err(DaoError::from)
     }
 
-    pub fn get(&self, conn: &MysqlConnection, id: String) -> DaoResult<domain::photo::Photo> {
-        photos::table
-            .find(id)
-            .first(conn)
-            .map(|v: Entity| domain::photo::Photo::try_from(v).unwrap())
-            .map_err(DaoError::from)
+    /// Every photo in `ids` that exists, ordered by id.
+    pub fn get_all(
+        &self,
+        conn: &DbConnection,
+        ids: Vec<PhotoId>,
+    ) -> DaoResult<Vec<domain::photo::Photo>> {
+        ddb::traced(
+            "photo.get_all",
+            photos::table
+                .filter(photos::id.eq_any(ids))
+                .order(photos::id.asc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::photo::Photo::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from)
     }
 
-    pub fn insert(&self, conn: &MysqlConnection, item: &domain::photo::Photo) -> DaoResult<()> {
+    pub fn insert(&self, conn: &DbConnection, item: &domain::photo::Photo) -> DaoResult<()> {
         let e: Entity = item.clone().into();
-        if let Err(e) = diesel::insert_into(photos::table)
-            .values(e)
-            .execute(conn)
-            .map_err(DaoError::from)
+        if let Err(e) = ddb::traced(
+            "photo.insert",
+            diesel::insert_into(photos::table).values(e),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
         {
             return Err(e);
         }
         Ok(())
     }
 
-    pub fn update(&self, conn: &MysqlConnection, item: &domain::photo::Photo) -> DaoResult<()> {
+    /// Inserts `items` with a single multi-row statement, or one statement per row on SQLite.
+    pub fn insert_all(
+        &self,
+        conn: &DbConnection,
+        items: &[domain::photo::Photo],
+    ) -> DaoResult<usize> {
+        let entities = items.iter().cloned().map(Entity::from).collect::<Vec<_>>();
+        #[cfg(not(feature = "sqlite"))]
+        let inserted = ddb::traced(
+            "photo.insert_all",
+            diesel::insert_into(photos::table).values(entities),
+            |q| q.execute(conn),
+        );
+
+        // diesel can't build a multi-row INSERT for SQLite, the caller's transaction keeps it atomic
+        #[cfg(feature = "sqlite")]
+        let inserted = entities.into_iter().try_fold(0, |inserted, e| {
+            ddb::traced(
+                "photo.insert_all",
+                diesel::insert_into(photos::table).values(e),
+                |q| q.execute(conn),
+            )
+            .map(|v| inserted + v)
+        });
+
+        inserted.map_err(DaoError::from)
+    }
+
+    pub fn update(&self, conn: &DbConnection, item: &domain::photo::Photo) -> DaoResult<()> {
         let e: Entity = item.clone().into();
-        if let Err(e) = diesel::update(photos::table.find(e.id))
-            .set((
-                photos::is_public.eq(e.is_public),
-                photos::updated_at.eq(e.updated_at),
-            ))
-            .execute(conn)
-            .map_err(DaoError::from)
-        {
-            return Err(e);
+        // the domain has already bumped `version`, so the row must still hold the previous one
+        let query = diesel::update(
+            photos::table
+                .filter(photos::id.eq(e.id))
+                .filter(photos::version.eq(e.version - 1)),
+        )
+        .set((
+            photos::is_public.eq(e.is_public),
+            photos::version.eq(e.version),
+            photos::updated_at.eq(e.updated_at),
+        ));
+        let updated =
+            ddb::traced("photo.update", query, |q| q.execute(conn)).map_err(DaoError::from)?;
+        if updated == 0 {
+            return Err(DaoError::Conflict);
         }
         Ok(())
     }
 
-    pub fn delete(&self, conn: &MysqlConnection, id: String) -> DaoResult<bool> {
-        if let Err(e) = diesel::delete(photos::table.find(id))
-            .execute(conn)
-            .map_err(DaoError::from)
+    pub fn delete(&self, conn: &DbConnection, id: PhotoId) -> DaoResult<bool> {
+        if let Err(e) = ddb::traced(
+            "photo.delete",
+            diesel::delete(photos::table.find(id)),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
         {
             return Err(e);
         }
         Ok(true)
     }
 
-    fn batch_get_all_by_user(
+    /// Owner of every photo in `ids` that exists, locking the rows until the transaction ends.
+    pub fn get_owners_for_update(
         &self,
-        conn: &MysqlConnection,
-        hashmap: &mut HashMap<String, DaoResult<Vec<domain::photo::Photo>>>,
-        user_ids: Vec<String>,
+        conn: &DbConnection,
+        ids: Vec<PhotoId>,
+    ) -> DaoResult<HashMap<PhotoId, UserId>> {
+        let query = photos::table
+            .select((photos::id, photos::user_id))
+            .filter(photos::id.eq_any(ids));
+        // SQLite has no row locks, it serialises writers for the whole database instead
+        #[cfg(not(feature = "sqlite"))]
+        let query = query.for_update();
+
+        ddb::traced("photo.get_owners_for_update", query, |q| {
+            q.load::<(PhotoId, UserId)>(conn)
+        })
+        .map(|v| v.into_iter().collect::<HashMap<_, _>>())
+        .map_err(DaoError::from)
+    }
+
+    pub fn update_visibility_all(
+        &self,
+        conn: &DbConnection,
+        ids: Vec<PhotoId>,
+        is_public: bool,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize> {
+        ddb::traced(
+            "photo.update_visibility_all",
+            diesel::update(photos::table.filter(photos::id.eq_any(ids))).set((
+                photos::is_public.eq(is_public),
+                photos::version.eq(photos::version + 1),
+                photos::updated_at.eq(now),
+            )),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
+    }
+
+    pub fn delete_all(&self, conn: &DbConnection, ids: Vec<PhotoId>) -> DaoResult<usize> {
+        ddb::traced(
+            "photo.delete_all",
+            diesel::delete(photos::table.filter(photos::id.eq_any(ids))),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
+    }
+
+    pub fn delete_all_by_user(&self, conn: &DbConnection, user_id: UserId) -> DaoResult<usize> {
+        ddb::traced(
+            "photo.delete_all_by_user",
+            diesel::delete(photos::table.filter(photos::user_id.eq(user_id))),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
+    }
+
+    /// Moves every photo of `from_user_id` to `to_user_id` and returns how many were moved.
+    pub fn reassign(
+        &self,
+        conn: &DbConnection,
+        from_user_id: UserId,
+        to_user_id: UserId,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize> {
+        ddb::traced(
+            "photo.reassign",
+            diesel::update(photos::table.filter(photos::user_id.eq(from_user_id))).set((
+                photos::user_id.eq(to_user_id),
+                photos::version.eq(photos::version + 1),
+                photos::updated_at.eq(now),
+            )),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
+    }
+
+    pub fn batch_get_all_by_user(
+        &self,
+        conn: &DbConnection,
+        hashmap: &mut HashMap<UserId, DaoResult<Vec<domain::photo::Photo>>>,
+        user_ids: Vec<UserId>,
     ) {
-        let result: DaoResult<Vec<domain::photo::Photo>> = photos::table
-            .filter(photos::user_id.eq_any(user_ids.clone()))
-            .order(photos::created_at.desc())
-            .load::<Entity>(conn)
-            .map(|v: Vec<Entity>| {
-                v.into_iter()
-                    .map(|v| domain::photo::Photo::try_from(v).unwrap())
-                    .collect::<Vec<_>>()
-            })
-            .map_err(DaoError::from);
+        let result: DaoResult<Vec<domain::photo::Photo>> = ddb::traced(
+            "photo.batch_get_all_by_user",
+            photos::table
+                .filter(photos::user_id.eq_any(user_ids.clone()))
+                .order(photos::created_at.desc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::photo::Photo::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from);
 
         if let Err(e) = result {
             for id in user_ids {
@@ -165,38 +387,7 @@ impl Dao<domain::photo::Photo> {
             for row in items.iter().filter(|v| v.user_id == id) {
                 vec.push(row.to_owned());
             }
-            hashmap.insert(id.to_owned(), Ok(vec));
+            hashmap.insert(id, Ok(vec));
         }
     }
 }
-
-pub struct BatchImpl {
-    dao: Dao<domain::photo::Photo>,
-    conn: Arc<Mutex<MysqlConnection>>,
-}
-
-#[async_trait]
-impl BatchFn<String, DaoResult<Vec<domain::photo::Photo>>> for BatchImpl {
-    async fn load(
-        &mut self,
-        keys: &[String],
-    ) -> HashMap<String, DaoResult<Vec<domain::photo::Photo>>> {
-        let conn = self.conn.lock().unwrap();
-        let mut hashmap = HashMap::new();
-        self.dao
-            .batch_get_all_by_user(&conn, &mut hashmap, keys.to_vec());
-        hashmap
-    }
-}
-
-impl BatchImpl {
-    pub fn new_loader(conn: Arc<Mutex<MysqlConnection>>) -> Loader {
-        cached::Loader::new(BatchImpl {
-            dao: Dao::new(),
-            conn,
-        })
-        .with_max_batch_size(100)
-    }
-}
-
-pub type Loader = cached::Loader<String, DaoResult<Vec<domain::photo::Photo>>, BatchImpl>;
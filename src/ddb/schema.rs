@@ -1,9 +1,37 @@
 table! {
-    photos (id) {
+    audit_events (id) {
+        id -> Binary,
+        actor_id -> Binary,
+        action -> Varchar,
+        entity_type -> Varchar,
+        entity_id -> Varchar,
+        changes -> Text,
+        request_id -> Varchar,
+        created_at -> Datetime,
+    }
+}
+
+table! {
+    data_exports (id) {
         id -> Varchar,
-        user_id -> Varchar,
+        user_id -> Binary,
+        status -> Varchar,
+        format_version -> Integer,
+        file_name -> Nullable<Varchar>,
+        error -> Nullable<Text>,
+        expires_at -> Nullable<Datetime>,
+        created_at -> Datetime,
+        updated_at -> Datetime,
+    }
+}
+
+table! {
+    photos (id) {
+        id -> Binary,
+        user_id -> Binary,
         url -> Varchar,
         is_public -> Bool,
+        version -> Integer,
         created_at -> Datetime,
         updated_at -> Datetime,
     }
@@ -11,16 +39,20 @@ table! {
 
 table! {
     users (id) {
-        id -> Varchar,
+        id -> Binary,
         name -> Varchar,
+        version -> Integer,
         created_at -> Datetime,
         updated_at -> Datetime,
     }
 }
 
+joinable!(data_exports -> users (user_id));
 joinable!(photos -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    audit_events,
+    data_exports,
     photos,
     users,
 );
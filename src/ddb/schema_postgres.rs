@@ -0,0 +1,58 @@
+table! {
+    audit_events (id) {
+        id -> Bytea,
+        actor_id -> Bytea,
+        action -> Varchar,
+        entity_type -> Varchar,
+        entity_id -> Varchar,
+        changes -> Text,
+        request_id -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    data_exports (id) {
+        id -> Varchar,
+        user_id -> Bytea,
+        status -> Varchar,
+        format_version -> Integer,
+        file_name -> Nullable<Varchar>,
+        error -> Nullable<Text>,
+        expires_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    photos (id) {
+        id -> Bytea,
+        user_id -> Bytea,
+        url -> Varchar,
+        is_public -> Bool,
+        version -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    users (id) {
+        id -> Bytea,
+        name -> Varchar,
+        version -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+joinable!(data_exports -> users (user_id));
+joinable!(photos -> users (user_id));
+
+allow_tables_to_appear_in_same_query!(
+    audit_events,
+    data_exports,
+    photos,
+    users,
+);
@@ -1,31 +1,38 @@
+use crate::ddb;
 use crate::ddb::photo;
 use crate::ddb::schema::photos;
 use crate::ddb::schema::users;
-use crate::ddb::{Dao, DaoError, DaoResult};
+use crate::ddb::{Dao, DaoError, DaoResult, DbConnection, OrderBy, SortDirection};
 use crate::domain;
-use async_trait::async_trait;
-use dataloader::{cached, BatchFn};
+use crate::domain::id::UserId;
 use diesel::prelude::*;
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::sync::{Arc, Mutex};
 
 #[derive(Queryable, Insertable, Debug, Clone, Eq, PartialEq, Identifiable)]
 #[table_name = "users"]
 pub struct Entity {
-    pub id: String,
+    pub id: UserId,
     pub name: String,
+    pub version: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
+impl ddb::Rows for Entity {
+    fn rows(&self) -> usize {
+        1
+    }
+}
+
 impl TryFrom<Entity> for domain::user::User {
     type Error = String;
 
     fn try_from(e: Entity) -> Result<Self, Self::Error> {
         Ok(domain::user::User {
-            id: e.id.to_string(),
-            name: e.name.to_string(),
+            id: e.id,
+            name: e.name,
+            version: e.version,
             created_at: e.created_at,
             updated_at: e.updated_at,
         })
@@ -37,27 +44,74 @@ impl From<domain::user::User> for Entity {
         Entity {
             id: d.id,
             name: d.name,
+            version: d.version,
             created_at: d.created_at,
             updated_at: d.updated_at,
         }
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub exclude_id: Option<UserId>,
+    pub name_contains: Option<String>,
+}
+
+/// Columns users can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderField {
+    /// Backed by `idx_users_created_at`.
+    CreatedAt,
+    /// Sorted by `LOWER(name)`, which `idx_users_name` doesn't cover, so it's a full sort.
+    Name,
+}
+
+impl Default for OrderBy<OrderField> {
+    fn default() -> Self {
+        OrderBy {
+            field: OrderField::CreatedAt,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+// each backend's collation orders mixed case differently, `LOWER` makes them agree
+sql_function!(fn lower(v: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Case-insensitive substring match on `users.name`, like MySQL's default collation and SQLite's `LIKE`.
+///
+/// The leading wildcard means no index can serve it, every row is scanned.
+fn name_contains(
+    v: &str,
+) -> Box<dyn BoxableExpression<users::table, ddb::Backend, SqlType = diesel::sql_types::Bool>> {
+    #[cfg(not(feature = "postgres"))]
+    let condition = users::name.like(ddb::contains_pattern(v)).escape('\\');
+    // backslash is already the default escape character of ILIKE
+    #[cfg(feature = "postgres")]
+    let condition = users::name.ilike(ddb::contains_pattern(v));
+
+    Box::new(condition)
+}
+
 impl Dao<domain::user::User> {
     pub fn get_all_with_photos(
         &self,
-        conn: &MysqlConnection,
+        conn: &DbConnection,
     ) -> DaoResult<Vec<(domain::user::User, Vec<domain::photo::Photo>)>> {
-        let user_entities = users::table
-            .order(users::created_at.desc())
-            .load::<Entity>(conn)
-            .map_err(DaoError::from)?;
+        let user_entities = ddb::traced(
+            "user.get_all_with_photos",
+            users::table.order(users::created_at.desc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map_err(DaoError::from)?;
 
-        let photo_entities = photo::Entity::belonging_to(&user_entities)
-            .order(photos::created_at.desc())
-            .load::<photo::Entity>(conn)
-            .map_err(DaoError::from)?
-            .grouped_by(&user_entities);
+        let photo_entities = ddb::traced(
+            "user.get_all_with_photos",
+            photo::Entity::belonging_to(&user_entities).order(photos::created_at.desc()),
+            |q| q.load::<photo::Entity>(conn),
+        )
+        .map_err(DaoError::from)?
+        .grouped_by(&user_entities);
 
         let zipped = user_entities
             .into_iter()
@@ -75,45 +129,78 @@ impl Dao<domain::user::User> {
         Ok(zipped)
     }
 
-    pub fn get_all_with_exclude(
+    pub fn find(
         &self,
-        conn: &MysqlConnection,
-        exclude_id: String,
+        conn: &DbConnection,
+        filter: &Filter,
+        order_by: OrderBy<OrderField>,
     ) -> DaoResult<Vec<domain::user::User>> {
-        return users::table
-            .filter(users::id.ne(exclude_id))
-            .order(users::created_at.desc())
-            .load::<Entity>(conn)
-            .map(|v: Vec<Entity>| {
-                v.into_iter()
-                    .map(|v| domain::user::User::try_from(v).unwrap())
-                    .collect::<Vec<_>>()
-            })
-            .map_err(DaoError::from);
+        let mut query = users::table.into_boxed::<ddb::Backend>();
+        if let Some(v) = filter.exclude_id {
+            query = query.filter(users::id.ne(v));
+        }
+        if let Some(v) = &filter.name_contains {
+            query = query.filter(name_contains(v));
+        }
+        query = match (order_by.field, order_by.direction) {
+            (OrderField::CreatedAt, SortDirection::Asc) => query.order(users::created_at.asc()),
+            (OrderField::CreatedAt, SortDirection::Desc) => query.order(users::created_at.desc()),
+            (OrderField::Name, SortDirection::Asc) => query.order(lower(users::name).asc()),
+            (OrderField::Name, SortDirection::Desc) => query.order(lower(users::name).desc()),
+        };
+
+        ddb::traced("user.find", query.then_order_by(users::id.asc()), |q| {
+            q.load::<Entity>(conn)
+        })
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::user::User::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from)
     }
 
-    pub fn get(&self, conn: &MysqlConnection, id: String) -> DaoResult<domain::user::User> {
-        users::table
-            .find(id)
-            .first(conn)
-            .map(|v: Entity| domain::user::User::try_from(v).unwrap())
-            .map_err(DaoError::from)
+    /// Users whose name contains `query`, `%` and `_` are matched literally.
+    pub fn search(&self, conn: &DbConnection, query: String) -> DaoResult<Vec<domain::user::User>> {
+        ddb::traced(
+            "user.search",
+            users::table
+                .filter(name_contains(&query))
+                .order(users::created_at.desc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::user::User::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from)
+    }
+
+    pub fn get(&self, conn: &DbConnection, id: UserId) -> DaoResult<domain::user::User> {
+        ddb::traced("user.get", users::table.find(id), |q| {
+            q.first::<Entity>(conn)
+        })
+        .map(|v: Entity| domain::user::User::try_from(v).unwrap())
+        .map_err(DaoError::from)
     }
 
     pub fn get_with_photos(
         &self,
-        conn: &MysqlConnection,
-        id: String,
+        conn: &DbConnection,
+        id: UserId,
     ) -> DaoResult<(domain::user::User, Vec<domain::photo::Photo>)> {
-        let user_entity = users::table
-            .find(id)
-            .first::<Entity>(conn)
-            .map_err(DaoError::from)?;
+        let user_entity = ddb::traced("user.get_with_photos", users::table.find(id), |q| {
+            q.first::<Entity>(conn)
+        })
+        .map_err(DaoError::from)?;
 
-        let photo_entities = photo::Entity::belonging_to(&user_entity)
-            .order(photos::created_at.desc())
-            .load::<photo::Entity>(conn)
-            .map_err(DaoError::from)?;
+        let photo_entities = ddb::traced(
+            "user.get_with_photos",
+            photo::Entity::belonging_to(&user_entity).order(photos::created_at.desc()),
+            |q| q.load::<photo::Entity>(conn),
+        )
+        .map_err(DaoError::from)?;
 
         Ok((
             domain::user::User::try_from(user_entity).unwrap(),
@@ -124,56 +211,71 @@ impl Dao<domain::user::User> {
         ))
     }
 
-    pub fn insert(&self, conn: &MysqlConnection, item: &domain::user::User) -> DaoResult<()> {
+    pub fn insert(&self, conn: &DbConnection, item: &domain::user::User) -> DaoResult<()> {
         let e: Entity = item.clone().into();
-        if let Err(e) = diesel::insert_into(users::table)
-            .values(e)
-            .execute(conn)
-            .map_err(DaoError::from)
+        if let Err(e) = ddb::traced(
+            "user.insert",
+            diesel::insert_into(users::table).values(e),
+            |q| q.execute(conn),
+        )
+        .map_err(DaoError::from)
         {
             return Err(e);
         }
         Ok(())
     }
 
-    pub fn update(&self, conn: &MysqlConnection, item: &domain::user::User) -> DaoResult<()> {
+    pub fn update(&self, conn: &DbConnection, item: &domain::user::User) -> DaoResult<()> {
         let e: Entity = item.clone().into();
-        if let Err(e) = diesel::update(users::table.find(e.id))
-            .set((users::name.eq(e.name), users::updated_at.eq(e.updated_at)))
-            .execute(conn)
-            .map_err(DaoError::from)
-        {
-            return Err(e);
+        // the domain has already bumped `version`, so the row must still hold the previous one
+        let query = diesel::update(
+            users::table
+                .filter(users::id.eq(e.id))
+                .filter(users::version.eq(e.version - 1)),
+        )
+        .set((
+            users::name.eq(e.name),
+            users::version.eq(e.version),
+            users::updated_at.eq(e.updated_at),
+        ));
+        let updated =
+            ddb::traced("user.update", query, |q| q.execute(conn)).map_err(DaoError::from)?;
+        if updated == 0 {
+            return Err(DaoError::Conflict);
         }
         Ok(())
     }
 
-    pub fn delete(&self, conn: &MysqlConnection, id: String) -> DaoResult<bool> {
-        if let Err(e) = diesel::delete(users::table.find(id))
-            .execute(conn)
-            .map_err(DaoError::from)
+    pub fn delete(&self, conn: &DbConnection, id: UserId) -> DaoResult<bool> {
+        if let Err(e) = ddb::traced("user.delete", diesel::delete(users::table.find(id)), |q| {
+            q.execute(conn)
+        })
+        .map_err(DaoError::from)
         {
             return Err(e);
         }
         Ok(true)
     }
 
-    fn batch_get(
+    pub fn batch_get(
         &self,
-        conn: &MysqlConnection,
-        hashmap: &mut HashMap<String, DaoResult<Vec<domain::user::User>>>,
-        ids: Vec<String>,
+        conn: &DbConnection,
+        hashmap: &mut HashMap<UserId, DaoResult<Vec<domain::user::User>>>,
+        ids: Vec<UserId>,
     ) {
-        let result: DaoResult<Vec<domain::user::User>> = users::table
-            .filter(users::id.eq_any(ids.clone()))
-            .order(users::created_at.desc())
-            .load::<Entity>(conn)
-            .map(|v: Vec<Entity>| {
-                v.into_iter()
-                    .map(|v| domain::user::User::try_from(v).unwrap())
-                    .collect::<Vec<_>>()
-            })
-            .map_err(DaoError::from);
+        let result: DaoResult<Vec<domain::user::User>> = ddb::traced(
+            "user.batch_get",
+            users::table
+                .filter(users::id.eq_any(ids.clone()))
+                .order(users::created_at.desc()),
+            |q| q.load::<Entity>(conn),
+        )
+        .map(|v: Vec<Entity>| {
+            v.into_iter()
+                .map(|v| domain::user::User::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .map_err(DaoError::from);
 
         if let Err(e) = result {
             for id in ids {
@@ -189,37 +291,7 @@ impl Dao<domain::user::User> {
             for row in items.iter().filter(|v| v.id == id) {
                 vec.push(row.to_owned());
             }
-            hashmap.insert(id.to_owned(), Ok(vec));
+            hashmap.insert(id, Ok(vec));
         }
     }
 }
-
-pub struct BatchImpl {
-    dao: Dao<domain::user::User>,
-    conn: Arc<Mutex<MysqlConnection>>,
-}
-
-#[async_trait]
-impl BatchFn<String, DaoResult<Vec<domain::user::User>>> for BatchImpl {
-    async fn load(
-        &mut self,
-        keys: &[String],
-    ) -> HashMap<String, DaoResult<Vec<domain::user::User>>> {
-        let conn = self.conn.lock().unwrap();
-        let mut hashmap = HashMap::new();
-        self.dao.batch_get(&conn, &mut hashmap, keys.to_vec());
-        hashmap
-    }
-}
-
-impl BatchImpl {
-    pub fn new_loader(conn: Arc<Mutex<MysqlConnection>>) -> Loader {
-        cached::Loader::new(BatchImpl {
-            dao: Dao::new(),
-            conn,
-        })
-        .with_max_batch_size(100)
-    }
-}
-
-pub type Loader = cached::Loader<String, DaoResult<Vec<domain::user::User>>, BatchImpl>;
@@ -1,2 +1,6 @@
+pub mod audit_event;
+pub mod clock;
+pub mod data_export;
+pub mod id;
 pub mod photo;
 pub mod user;
@@ -0,0 +1,191 @@
+use crate::domain::clock::Clock;
+use crate::domain::data_export::DataExport;
+use crate::domain::id::{AuditEventId, IdGenerator, UserId};
+use crate::domain::photo::Photo;
+use crate::domain::user::User;
+use serde_json::{json, Map, Value};
+
+/// An entity whose changes are written to the audit log.
+pub trait Audited {
+    fn entity_type(&self) -> &'static str;
+    fn entity_id(&self) -> String;
+    /// The fields a change is recorded for, named as in the GraphQL schema.
+    fn audit_state(&self) -> Map<String, Value>;
+}
+
+impl Audited for User {
+    fn entity_type(&self) -> &'static str {
+        "user"
+    }
+
+    fn entity_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn audit_state(&self) -> Map<String, Value> {
+        let mut state = Map::new();
+        state.insert("name".to_string(), json!(self.name));
+        state.insert("version".to_string(), json!(self.version));
+        state
+    }
+}
+
+impl Audited for Photo {
+    fn entity_type(&self) -> &'static str {
+        "photo"
+    }
+
+    fn entity_id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn audit_state(&self) -> Map<String, Value> {
+        let mut state = Map::new();
+        state.insert("userId".to_string(), json!(self.user_id.to_string()));
+        state.insert("url".to_string(), json!(self.url));
+        state.insert("isPublic".to_string(), json!(self.is_public));
+        state.insert("version".to_string(), json!(self.version));
+        state
+    }
+}
+
+impl Audited for DataExport {
+    fn entity_type(&self) -> &'static str {
+        "data_export"
+    }
+
+    fn entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn audit_state(&self) -> Map<String, Value> {
+        let mut state = Map::new();
+        state.insert("userId".to_string(), json!(self.user_id.to_string()));
+        state.insert("status".to_string(), json!(self.status.as_str()));
+        state.insert("formatVersion".to_string(), json!(self.format_version));
+        state
+    }
+}
+
+/// What a mutation did to one entity.
+#[derive(Debug)]
+pub enum Change<'a, T> {
+    Created(&'a T),
+    Updated { before: &'a T, after: &'a T },
+    Deleted(&'a T),
+}
+
+impl<'a, T: Audited> Change<'a, T> {
+    fn entity(&self) -> &'a T {
+        match *self {
+            Change::Created(v) | Change::Deleted(v) => v,
+            Change::Updated { after, .. } => after,
+        }
+    }
+
+    /// Every field that differs, `null` on the missing side of a creation or deletion.
+    fn diff(&self) -> Vec<FieldChange> {
+        let (before, after) = match *self {
+            Change::Created(v) => (Map::new(), v.audit_state()),
+            Change::Updated { before, after } => (before.audit_state(), after.audit_state()),
+            Change::Deleted(v) => (v.audit_state(), Map::new()),
+        };
+
+        let added = after.keys().filter(|v| !before.contains_key(*v));
+        before
+            .keys()
+            .chain(added)
+            .filter_map(|key| {
+                let old = before.get(key).cloned().unwrap_or(Value::Null);
+                let new = after.get(key).cloned().unwrap_or(Value::Null);
+                if old == new {
+                    return None;
+                }
+                Some(FieldChange {
+                    field: key.clone(),
+                    before: old,
+                    after: new,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The value of one field before and after a change.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl FieldChange {
+    /// `{"field": {"before": .., "after": ..}}`, as stored in `audit_events.changes`.
+    pub fn encode(changes: &[FieldChange]) -> String {
+        let mut v = Map::new();
+        for change in changes {
+            v.insert(
+                change.field.clone(),
+                json!({ "before": change.before, "after": change.after }),
+            );
+        }
+        Value::Object(v).to_string()
+    }
+
+    pub fn decode(v: &str) -> Result<Vec<FieldChange>, String> {
+        let object = match serde_json::from_str::<Value>(v) {
+            Ok(Value::Object(v)) => v,
+            Ok(_) => return Err("invalid audit changes: not an object".to_string()),
+            Err(e) => return Err(format!("invalid audit changes: {}", e)),
+        };
+
+        Ok(object
+            .into_iter()
+            .map(|(field, mut v)| FieldChange {
+                field,
+                before: v.get_mut("before").map(Value::take).unwrap_or(Value::Null),
+                after: v.get_mut("after").map(Value::take).unwrap_or(Value::Null),
+            })
+            .collect())
+    }
+}
+
+/// One change made by a mutation, written in the same transaction as the change itself.
+///
+/// There are no foreign keys, events outlive the actor and the entity.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditEvent {
+    pub id: AuditEventId,
+    pub actor_id: UserId,
+    /// The mutation, e.g. `updatePhoto`.
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    /// The fields that differ, in the order the entity lists them.
+    pub changes: Vec<FieldChange>,
+    pub request_id: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl AuditEvent {
+    pub fn new<T: Audited>(
+        actor_id: UserId,
+        action: &str,
+        change: Change<T>,
+        request_id: &str,
+        ids: &dyn IdGenerator,
+        clock: &dyn Clock,
+    ) -> Self {
+        let entity = change.entity();
+        AuditEvent {
+            id: AuditEventId::from(ids.generate()),
+            actor_id,
+            action: action.to_string(),
+            entity_type: entity.entity_type().to_string(),
+            entity_id: entity.entity_id(),
+            changes: change.diff(),
+            request_id: request_id.to_string(),
+            created_at: clock.now().naive_utc(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::domain::clock::Clock;
+use crate::domain::id::{IdGenerator, UserId};
+use chrono::{DateTime, Duration, Utc};
+
+/// Bumped whenever the layout of the archive changes, see `docs/data-export.md`.
+pub const FORMAT_VERSION: i32 = 2;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DataExportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+impl DataExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataExportStatus::Pending => "pending",
+            DataExportStatus::Running => "running",
+            DataExportStatus::Completed => "completed",
+            DataExportStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(v: &str) -> Result<Self, String> {
+        match v {
+            "pending" => Ok(DataExportStatus::Pending),
+            "running" => Ok(DataExportStatus::Running),
+            "completed" => Ok(DataExportStatus::Completed),
+            "failed" => Ok(DataExportStatus::Failed),
+            _ => Err(format!("unknown data export status: {}", v)),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, DataExportStatus::Pending | DataExportStatus::Running)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DataExport {
+    pub id: String,
+    pub user_id: UserId,
+    pub status: DataExportStatus,
+    pub format_version: i32,
+    pub file_name: Option<String>,
+    pub error: Option<String>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl DataExport {
+    pub fn new(user_id: UserId, ids: &dyn IdGenerator, clock: &dyn Clock) -> Self {
+        let now = clock.now();
+        DataExport {
+            id: ids.generate().to_string(),
+            user_id,
+            status: DataExportStatus::Pending,
+            format_version: FORMAT_VERSION,
+            file_name: None,
+            error: None,
+            expires_at: None,
+            created_at: now.naive_utc(),
+            updated_at: now.naive_utc(),
+        }
+    }
+
+    pub fn start(&mut self, now: DateTime<Utc>) {
+        self.status = DataExportStatus::Running;
+        self.updated_at = now.naive_utc();
+    }
+
+    pub fn complete(&mut self, file_name: String, retention: Duration, now: DateTime<Utc>) {
+        self.status = DataExportStatus::Completed;
+        self.file_name = Some(file_name);
+        self.expires_at = Some((now + retention).naive_utc());
+        self.updated_at = now.naive_utc();
+    }
+
+    pub fn fail(&mut self, error: String, now: DateTime<Utc>) {
+        self.status = DataExportStatus::Failed;
+        self.error = Some(error);
+        self.updated_at = now.naive_utc();
+    }
+
+    /// The archive was deleted after `expires_at`, the export stays listed.
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        self.file_name = None;
+        self.updated_at = now.naive_utc();
+    }
+
+    pub fn is_downloadable(&self, now: DateTime<Utc>) -> bool {
+        self.status == DataExportStatus::Completed
+            && self.expires_at.map_or(false, |v| v > now.naive_utc())
+    }
+}
@@ -0,0 +1,134 @@
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Binary;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// A 128-bit id, stored as 16 raw bytes and written as a hyphenated UUID everywhere else.
+/// Each kind of id is its own type, so a photo id can't be passed where a user id is expected.
+macro_rules! id_type {
+    ($name:ident) => {
+        #[derive(
+            Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, AsExpression, FromSqlRow,
+        )]
+        #[sql_type = "Binary"]
+        pub struct $name(Uuid);
+
+        impl $name {
+            pub fn as_bytes(&self) -> &[u8; 16] {
+                self.0.as_bytes()
+            }
+
+            pub fn from_slice(v: &[u8]) -> Result<Self, String> {
+                Uuid::from_slice(v)
+                    .map($name)
+                    .map_err(|e| format!("invalid {}: {}", stringify!($name), e))
+            }
+        }
+
+        impl From<Uuid> for $name {
+            fn from(v: Uuid) -> Self {
+                $name(v)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(v: &str) -> Result<Self, Self::Err> {
+                Uuid::parse_str(v)
+                    .map($name)
+                    .map_err(|e| format!("invalid {}: {}", stringify!($name), e))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl<DB: Backend> ToSql<Binary, DB> for $name
+        where
+            [u8]: ToSql<Binary, DB>,
+        {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, DB>) -> serialize::Result {
+                <[u8] as ToSql<Binary, DB>>::to_sql(&self.as_bytes()[..], out)
+            }
+        }
+
+        impl<DB: Backend> FromSql<Binary, DB> for $name
+        where
+            Vec<u8>: FromSql<Binary, DB>,
+        {
+            fn from_sql(bytes: Option<&DB::RawValue>) -> deserialize::Result<Self> {
+                let bytes = <Vec<u8> as FromSql<Binary, DB>>::from_sql(bytes)?;
+                $name::from_slice(&bytes).map_err(Into::into)
+            }
+        }
+    };
+}
+
+id_type!(UserId);
+id_type!(PhotoId);
+id_type!(AuditEventId);
+
+/// A UUID whose first 48 bits are `millis` since the Unix epoch, laid out like the draft UUIDv7,
+/// so ids created later sort later and new rows land at the end of the primary key index.
+pub fn time_ordered(millis: u64, random: [u8; 16]) -> Uuid {
+    let mut bytes = random;
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x70;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Uuid::from_bytes(bytes)
+}
+
+/// Source of the ids given to new users, photos and data exports.
+pub trait IdGenerator: Send + Sync {
+    fn generate(&self) -> Uuid;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeOrderedIdGenerator;
+
+impl IdGenerator for TimeOrderedIdGenerator {
+    fn generate(&self) -> Uuid {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|v| v.as_millis() as u64)
+            .unwrap_or_default();
+        time_ordered(millis, *Uuid::new_v4().as_bytes())
+    }
+}
+
+/// `00000000-0000-0000-0000-000000000001`, `...0002` and so on, for tests.
+#[derive(Debug)]
+pub struct SequentialIdGenerator {
+    next: AtomicU64,
+}
+
+impl SequentialIdGenerator {
+    pub fn new() -> Self {
+        SequentialIdGenerator {
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl Default for SequentialIdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn generate(&self) -> Uuid {
+        let n = self.next.fetch_add(1, Ordering::SeqCst);
+        Uuid::from_u128(u128::from(n))
+    }
+}
@@ -1,23 +1,33 @@
+use crate::domain::clock::Clock;
+use crate::domain::id::{IdGenerator, PhotoId, UserId};
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Photo {
-    pub id: String,
-    pub user_id: String,
+    pub id: PhotoId,
+    pub user_id: UserId,
     pub url: String,
     pub is_public: bool,
+    pub version: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
 impl Photo {
-    pub fn new(user_id: String, url: String, is_public: bool, now: DateTime<Utc>) -> Self {
+    pub fn new(
+        user_id: UserId,
+        url: String,
+        is_public: bool,
+        ids: &dyn IdGenerator,
+        clock: &dyn Clock,
+    ) -> Self {
+        let now = clock.now();
         Photo {
-            id: Uuid::new_v4().to_string(),
+            id: PhotoId::from(ids.generate()),
             user_id,
             url,
             is_public,
+            version: 1,
             created_at: now.naive_utc(),
             updated_at: now.naive_utc(),
         }
@@ -25,6 +35,7 @@ impl Photo {
 
     pub fn update_visibility(&mut self, is_public: bool, now: DateTime<Utc>) {
         self.is_public = is_public;
+        self.version += 1;
         self.updated_at = now.naive_utc();
     }
 }
@@ -1,19 +1,23 @@
+use crate::domain::clock::Clock;
+use crate::domain::id::{IdGenerator, UserId};
 use chrono::{DateTime, Utc};
-use uuid::Uuid;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct User {
-    pub id: String,
+    pub id: UserId,
     pub name: String,
+    pub version: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
 
 impl User {
-    pub fn new(name: String, now: DateTime<Utc>) -> Self {
+    pub fn new(name: String, ids: &dyn IdGenerator, clock: &dyn Clock) -> Self {
+        let now = clock.now();
         User {
-            id: Uuid::new_v4().to_string(),
+            id: UserId::from(ids.generate()),
             name,
+            version: 1,
             created_at: now.naive_utc(),
             updated_at: now.naive_utc(),
         }
@@ -21,6 +25,7 @@ impl User {
 
     pub fn update(&mut self, name: String, now: DateTime<Utc>) {
         self.name = name;
+        self.version += 1;
         self.updated_at = now.naive_utc();
     }
 }
@@ -0,0 +1,216 @@
+use crate::config::ExportsConfig;
+use crate::ddb::{self, Dao, DaoError, DaoResult};
+use crate::domain;
+use crate::domain::clock::Clock;
+use crate::domain::data_export::{DataExport, FORMAT_VERSION};
+use actix_web::{rt, web};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde_json::json;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+pub const DOWNLOAD_PATH: &str = "/exports";
+
+/// Builds "download my data" archives in the background, the layout is described in `docs/data-export.md`.
+pub struct DataExporter {
+    pool: ddb::Pool,
+    dir: PathBuf,
+    retention: Duration,
+    stale_after: Duration,
+    sweep_interval: std::time::Duration,
+    clock: Arc<dyn Clock>,
+}
+
+/// What one `DataExporter::sweep` changed.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct SweepReport {
+    pub failed: usize,
+    pub expired: usize,
+}
+
+impl DataExporter {
+    pub fn new(pool: ddb::Pool, config: &ExportsConfig, clock: Arc<dyn Clock>) -> Self {
+        DataExporter {
+            pool,
+            dir: PathBuf::from(&config.dir),
+            retention: Duration::hours(config.retention_hours),
+            stale_after: Duration::minutes(config.stale_after_minutes),
+            sweep_interval: std::time::Duration::from_secs(config.sweep_interval_minutes * 60),
+            clock,
+        }
+    }
+
+    pub fn download_url(export_id: &str) -> String {
+        format!("{}/{}", DOWNLOAD_PATH, export_id)
+    }
+
+    pub fn path(&self, file_name: &str) -> PathBuf {
+        self.dir.join(file_name)
+    }
+
+    /// Builds the archive for a pending export on the blocking pool, so the request can return right away.
+    pub fn spawn(self: &Arc<Self>, export_id: String) {
+        let exporter = Arc::clone(self);
+        rt::spawn(async move {
+            let id = export_id.clone();
+            if let Err(e) = web::block(move || exporter.run(export_id)).await {
+                error!(export_id = %id, error = %e, "data export failed");
+            }
+        });
+    }
+
+    /// Sweeps once right away, then every `exports.sweep_interval_minutes` for as long as the
+    /// process runs.
+    pub fn spawn_sweeper(self: &Arc<Self>) {
+        let exporter = Arc::clone(self);
+        rt::spawn(async move {
+            let mut interval = actix_rt::time::interval(exporter.sweep_interval);
+            loop {
+                interval.tick().await;
+                let sweeper = Arc::clone(&exporter);
+                match web::block(move || sweeper.sweep()).await {
+                    Ok(v) => info!(failed = v.failed, expired = v.expired, "data exports swept"),
+                    Err(e) => error!(error = %e, "sweeping data exports failed"),
+                }
+            }
+        });
+    }
+
+    /// Fails exports that stayed pending or running for longer than `exports.stale_after_minutes`,
+    /// they would otherwise block new requests forever, and deletes the archives of expired ones.
+    pub fn sweep(&self) -> DaoResult<SweepReport> {
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| DaoError::Internal(e.to_string()))?;
+        let dao = Dao::<DataExport>::new();
+        let now = self.clock.now();
+        let mut report = SweepReport::default();
+
+        for mut export in dao.get_stale(&conn, (now - self.stale_after).naive_utc())? {
+            export.fail("the export did not finish in time".to_string(), now);
+            dao.update(&conn, &export)?;
+            report.failed += 1;
+        }
+
+        for mut export in dao.get_expired(&conn, now.naive_utc())? {
+            let path = self.path(export.file_name.as_deref().unwrap_or_default());
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    // kept as is, so the next sweep tries again
+                    warn!(
+                        export_id = %export.id,
+                        error = %e,
+                        "failed to delete an expired data export"
+                    );
+                    continue;
+                }
+            }
+            export.expire(now);
+            dao.update(&conn, &export)?;
+            report.expired += 1;
+        }
+
+        Ok(report)
+    }
+
+    fn run(&self, export_id: String) -> DaoResult<()> {
+        let conn = self
+            .pool
+            .get()
+            .map_err(|e| DaoError::Internal(e.to_string()))?;
+        let dao = Dao::<DataExport>::new();
+
+        let mut export = dao.get(&conn, export_id)?;
+        export.start(self.clock.now());
+        dao.update(&conn, &export)?;
+
+        match self.build(&conn, &export) {
+            Ok(file_name) => {
+                export.complete(file_name, self.retention, self.clock.now());
+                info!(export_id = %export.id, "data export completed");
+            }
+            Err(e) => export.fail(e, self.clock.now()),
+        }
+        dao.update(&conn, &export)?;
+
+        match &export.error {
+            Some(e) => Err(DaoError::Internal(e.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn build(&self, conn: &ddb::Connection, export: &DataExport) -> Result<String, String> {
+        let (user, photos) = Dao::<domain::user::User>::new()
+            .get_with_photos(conn, export.user_id)
+            .map_err(|e| e.to_string())?;
+
+        let now: DateTime<Utc> = self.clock.now();
+        let entries = vec![
+            (
+                "manifest.json",
+                json!({
+                    "formatVersion": FORMAT_VERSION,
+                    "exportId": export.id,
+                    "userId": user.id.to_string(),
+                    "generatedAt": now.to_rfc3339(),
+                    "files": ["user.json", "photos.json"],
+                }),
+            ),
+            (
+                "user.json",
+                json!({
+                    "id": user.id.to_string(),
+                    "name": user.name,
+                    "version": user.version,
+                    "createdAt": rfc3339(user.created_at),
+                    "updatedAt": rfc3339(user.updated_at),
+                }),
+            ),
+            (
+                "photos.json",
+                json!(photos
+                    .iter()
+                    .map(|v| json!({
+                        "id": v.id.to_string(),
+                        "url": v.url,
+                        "isPublic": v.is_public,
+                        "version": v.version,
+                        "createdAt": rfc3339(v.created_at),
+                        "updatedAt": rfc3339(v.updated_at),
+                    }))
+                    .collect::<Vec<_>>()),
+            ),
+        ];
+
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let file_name = format!("{}.zip", export.id);
+        // written under a temporary name so a crash never leaves a truncated archive behind
+        let partial = self.path(&format!("{}.partial", file_name));
+        let file = File::create(&partial).map_err(|e| e.to_string())?;
+
+        let mut zip = ZipWriter::new(file);
+        for (name, body) in entries {
+            zip.start_file(name, FileOptions::default())
+                .map_err(|e| e.to_string())?;
+            let body = serde_json::to_vec_pretty(&body).map_err(|e| e.to_string())?;
+            zip.write_all(&body).map_err(|e| e.to_string())?;
+        }
+        zip.finish().map_err(|e| e.to_string())?;
+
+        fs::rename(&partial, self.path(&file_name)).map_err(|e| e.to_string())?;
+        Ok(file_name)
+    }
+}
+
+/// Stored timestamps are UTC without a zone, the archive writes them like `generatedAt`.
+fn rfc3339(v: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_utc(v, Utc).to_rfc3339()
+}
@@ -1,54 +1,143 @@
-mod errors;
+mod audit_event;
+mod data_export;
+pub mod errors;
+pub mod limits;
 mod me;
 mod mutation;
 mod other;
+pub mod persisted;
 mod photo;
 mod query;
+pub mod request;
 
 use self::mutation::*;
 use self::query::*;
+use crate::graphql::audit_event::*;
+use crate::graphql::data_export::*;
 use crate::graphql::me::*;
 use crate::graphql::other::*;
 use crate::graphql::photo::*;
 use juniper::*;
 use juniper_from_schema::graphql_schema_from_file;
 
-use crate::ddb;
-use diesel::MysqlConnection;
-use std::sync::{Arc, Mutex, MutexGuard};
+use self::errors::FieldErrorWithCode;
+use crate::ddb::DaoResult;
+use crate::domain;
+use crate::domain::audit_event::{Audited, Change};
+use crate::domain::clock::Clock;
+use crate::domain::id::IdGenerator;
+use crate::export::DataExporter;
+use crate::repository::{self, Repositories};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::sync::Arc;
 
 #[allow(unused)]
 graphql_schema_from_file!("src/graphql/schema.graphql", context_type: Context);
 
 pub struct Context {
-    pub authorized_user_id: Option<String>,
-    pub connection: Arc<Mutex<MysqlConnection>>,
-    pub user_loader: ddb::user::Loader,
-    pub photo_loader: ddb::photo::Loader,
+    pub authorized_user_id: Option<domain::id::UserId>,
+    /// Whether `authorized_user_id` is one of `auth.admin_user_ids`.
+    pub is_admin: bool,
+    pub request_id: String,
+    pub repositories: Repositories,
+    pub user_loader: repository::UserLoader,
+    pub photo_loader: repository::PhotoLoader,
+    pub exporter: Arc<DataExporter>,
+    pub clock: Arc<dyn Clock>,
+    pub ids: Arc<dyn IdGenerator>,
 }
 
 impl juniper::Context for Context {}
 
 impl Context {
-    pub fn new(authorized_user_id: Option<String>) -> Self {
-        let conn_ref = Arc::new(Mutex::new(ddb::establish_connection()));
+    pub fn new(
+        authorized_user_id: Option<domain::id::UserId>,
+        is_admin: bool,
+        request_id: String,
+        repositories: Repositories,
+        exporter: Arc<DataExporter>,
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+    ) -> Self {
         Self {
             authorized_user_id,
-            connection: Arc::clone(&conn_ref),
-            user_loader: ddb::user::BatchImpl::new_loader(Arc::clone(&conn_ref)),
-            photo_loader: ddb::photo::BatchImpl::new_loader(Arc::clone(&conn_ref)),
+            is_admin,
+            request_id,
+            user_loader: repository::new_user_loader(Arc::clone(&repositories.users)),
+            photo_loader: repository::new_photo_loader(Arc::clone(&repositories.photos)),
+            repositories,
+            exporter,
+            clock,
+            ids,
         }
     }
 
-    pub fn ddb_dao<T>(&self) -> ddb::Dao<T> {
-        ddb::Dao::new()
+    /// Writes one audit event per change, made by `actor_id` in this request. Mutations call it
+    /// inside their transaction, so the log and the data can't disagree.
+    fn audit<T: Audited>(
+        &self,
+        actor_id: domain::id::UserId,
+        action: &str,
+        changes: Vec<Change<T>>,
+    ) -> DaoResult<()> {
+        let events = changes
+            .into_iter()
+            .map(|v| {
+                domain::audit_event::AuditEvent::new(
+                    actor_id,
+                    action,
+                    v,
+                    &self.request_id,
+                    self.ids.as_ref(),
+                    self.clock.as_ref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        if !events.is_empty() {
+            self.repositories.audit_events.insert_all(&events)?;
+        }
+        Ok(())
+    }
+}
+
+// `UserId` and `PhotoId` are the string scalars generated from the schema, the domain ids are
+// only converted to and from them here.
+
+impl UserId {
+    /// The id this scalar holds, a value that isn't a UUID is a bad request.
+    fn parse(&self) -> Result<domain::id::UserId, FieldErrorWithCode> {
+        self.0
+            .parse()
+            .map_err(|_| FieldErrorWithCode::bad_request())
+    }
+}
+
+impl From<domain::id::UserId> for UserId {
+    fn from(v: domain::id::UserId) -> Self {
+        UserId(v.to_string())
     }
+}
 
-    pub fn get_mutex_connection(&self) -> MutexGuard<MysqlConnection> {
-        self.connection.lock().unwrap()
+impl PhotoId {
+    /// The id this scalar holds, a value that isn't a UUID is a bad request.
+    fn parse(&self) -> Result<domain::id::PhotoId, FieldErrorWithCode> {
+        self.0
+            .parse()
+            .map_err(|_| FieldErrorWithCode::bad_request())
     }
 }
 
+impl From<domain::id::PhotoId> for PhotoId {
+    fn from(v: domain::id::PhotoId) -> Self {
+        PhotoId(v.to_string())
+    }
+}
+
+/// Timestamps are stored without a zone, but always in UTC.
+fn utc(v: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_utc(v, Utc)
+}
+
 pub fn new_schema() -> Schema {
     Schema::new(Query {}, Mutation {}, EmptySubscription::new())
 }
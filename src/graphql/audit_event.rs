@@ -0,0 +1,152 @@
+use crate::ddb::audit_event::Cursor;
+use crate::domain;
+use crate::graphql::*;
+use juniper_from_schema::{QueryTrail, Walked};
+use serde_json::Value;
+
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub event: domain::audit_event::AuditEvent,
+}
+impl AuditEventFields for AuditEvent {
+    fn field_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
+        Ok(Into::into(self.event.id.to_string()))
+    }
+
+    fn field_actor_id(&self, _: &Executor<Context>) -> FieldResult<UserId> {
+        Ok(UserId::from(self.event.actor_id))
+    }
+
+    fn field_action(&self, _: &Executor<Context>) -> FieldResult<String> {
+        Ok(self.event.action.clone())
+    }
+
+    fn field_entity_type(&self, _: &Executor<Context>) -> FieldResult<String> {
+        Ok(self.event.entity_type.clone())
+    }
+
+    fn field_entity_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
+        Ok(Into::into(self.event.entity_id.clone()))
+    }
+
+    fn field_changes<'r>(
+        &self,
+        _: &Executor<Context>,
+        _: &QueryTrail<'r, AuditChange, Walked>,
+    ) -> FieldResult<Vec<AuditChange>> {
+        let changes = self
+            .event
+            .changes
+            .iter()
+            .map(|v| AuditChange {
+                change: v.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        Ok(changes)
+    }
+
+    fn field_request_id(&self, _: &Executor<Context>) -> FieldResult<String> {
+        Ok(self.event.request_id.clone())
+    }
+
+    fn field_created_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.event.created_at))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditChange {
+    pub change: domain::audit_event::FieldChange,
+}
+impl AuditChangeFields for AuditChange {
+    fn field_field(&self, _: &Executor<Context>) -> FieldResult<String> {
+        Ok(self.change.field.clone())
+    }
+
+    fn field_before(&self, _: &Executor<Context>) -> FieldResult<Option<String>> {
+        Ok(text(&self.change.before))
+    }
+
+    fn field_after(&self, _: &Executor<Context>) -> FieldResult<Option<String>> {
+        Ok(text(&self.change.after))
+    }
+}
+
+/// Strings as they are, other values as their JSON, `null` as none.
+fn text(v: &Value) -> Option<String> {
+    match v {
+        Value::Null => None,
+        Value::String(v) => Some(v.clone()),
+        v => Some(v.to_string()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEventEdge {
+    pub event: domain::audit_event::AuditEvent,
+}
+#[async_trait]
+impl AuditEventEdgeFields for AuditEventEdge {
+    async fn field_node<'s, 'r, 'a>(
+        &'s self,
+        _exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, AuditEvent, Walked>,
+    ) -> FieldResult<AuditEvent> {
+        Ok(AuditEvent {
+            event: self.event.clone(),
+        })
+    }
+
+    fn field_cursor(&self, _: &Executor<Context>) -> FieldResult<String> {
+        Ok(Cursor::of(&self.event).encode())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEventConnection {
+    pub events: Vec<domain::audit_event::AuditEvent>,
+    pub has_next_page: bool,
+}
+#[async_trait]
+impl AuditEventConnectionFields for AuditEventConnection {
+    async fn field_edges<'s, 'r, 'a>(
+        &'s self,
+        _exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, AuditEventEdge, Walked>,
+    ) -> FieldResult<Vec<AuditEventEdge>> {
+        let edges = self
+            .events
+            .iter()
+            .map(|v| AuditEventEdge {
+                event: v.to_owned(),
+            })
+            .collect::<Vec<_>>();
+        Ok(edges)
+    }
+
+    fn field_page_info<'r>(
+        &self,
+        _: &Executor<Context>,
+        _: &QueryTrail<'r, PageInfo, Walked>,
+    ) -> FieldResult<PageInfo> {
+        Ok(PageInfo {
+            has_next_page: self.has_next_page,
+            end_cursor: self.events.last().map(|v| Cursor::of(v).encode()),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+impl PageInfoFields for PageInfo {
+    fn field_has_next_page(&self, _: &Executor<Context>) -> FieldResult<bool> {
+        Ok(self.has_next_page)
+    }
+
+    fn field_end_cursor(&self, _: &Executor<Context>) -> FieldResult<Option<String>> {
+        Ok(self.end_cursor.clone())
+    }
+}
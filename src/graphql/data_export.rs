@@ -0,0 +1,38 @@
+use crate::domain;
+use crate::domain::data_export::DataExportStatus as Status;
+use crate::export::DataExporter;
+use crate::graphql::*;
+
+#[derive(Debug, Clone)]
+pub struct DataExport {
+    pub export: domain::data_export::DataExport,
+}
+impl DataExportFields for DataExport {
+    fn field_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
+        Ok(Into::into(self.export.id.clone()))
+    }
+
+    fn field_status(&self, _: &Executor<Context>) -> FieldResult<DataExportStatus> {
+        Ok(match self.export.status {
+            Status::Pending => DataExportStatus::Pending,
+            Status::Running => DataExportStatus::Running,
+            Status::Completed => DataExportStatus::Completed,
+            Status::Failed => DataExportStatus::Failed,
+        })
+    }
+
+    fn field_format_version(&self, _: &Executor<Context>) -> FieldResult<i32> {
+        Ok(self.export.format_version)
+    }
+
+    fn field_download_url(&self, exec: &Executor<Context>) -> FieldResult<Option<String>> {
+        if !self.export.is_downloadable(exec.context().clock.now()) {
+            return Ok(None);
+        }
+        Ok(Some(DataExporter::download_url(&self.export.id)))
+    }
+
+    fn field_error(&self, _: &Executor<Context>) -> FieldResult<Option<String>> {
+        Ok(self.export.error.clone())
+    }
+}
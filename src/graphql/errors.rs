@@ -1,6 +1,8 @@
 use crate::ddb::DaoError;
+use crate::metrics;
 use convert_case::{Case, Casing};
 use juniper::{graphql_value, FieldError};
+use serde_json::json;
 use strum_macros::Display as StrumDisplay;
 use thiserror::Error;
 
@@ -10,9 +12,22 @@ pub enum FieldErrorCode {
     UnAuthenticate,
     NotFound,
     Forbidden,
+    Conflict,
+    QueryTooDeep,
+    QueryTooComplex,
+    TooManyAliases,
+    PersistedQueryNotFound,
+    PersistedQueryNotAllowed,
+    RateLimited,
     Internal,
 }
 
+impl FieldErrorCode {
+    pub fn to_code_string(&self) -> String {
+        self.to_string().to_case(Case::UpperSnake)
+    }
+}
+
 #[derive(Error, Debug, Clone)]
 pub enum APIError {
     #[error("api error: {0}")]
@@ -61,15 +76,52 @@ impl From<DaoError> for FieldErrorWithCode {
             code: match err {
                 DaoError::NotFound => FieldErrorCode::NotFound,
                 DaoError::Forbidden => FieldErrorCode::Forbidden,
+                DaoError::Conflict => FieldErrorCode::Conflict,
                 DaoError::Internal(_) => FieldErrorCode::Internal,
             },
         }
     }
 }
 
+pub fn request_error_body(
+    code: FieldErrorCode,
+    message: String,
+    request_id: &str,
+) -> serde_json::Value {
+    json!({
+        "errors": [{
+            "message": message,
+            "extensions": {
+                "code": code.to_code_string(),
+                "requestId": request_id,
+            },
+        }],
+    })
+}
+
+/// Adds `requestId` to the extensions of every error in a single or batched response body.
+pub fn with_request_id(body: &mut serde_json::Value, request_id: &str) {
+    let responses = match body {
+        serde_json::Value::Array(v) => v.iter_mut().collect::<Vec<_>>(),
+        v => vec![v],
+    };
+
+    for response in responses {
+        if let Some(serde_json::Value::Array(errors)) = response.get_mut("errors") {
+            for error in errors.iter_mut().filter_map(|v| v.as_object_mut()) {
+                let extensions = error.entry("extensions").or_insert_with(|| json!({}));
+                if let Some(extensions) = extensions.as_object_mut() {
+                    extensions.insert("requestId".to_string(), json!(request_id));
+                }
+            }
+        }
+    }
+}
+
 impl From<FieldErrorWithCode> for FieldError {
     fn from(v: FieldErrorWithCode) -> Self {
-        let code = v.code.to_string().to_case(Case::UpperSnake);
+        let code = v.code.to_code_string();
+        metrics::observe_resolver_error(&code);
 
         FieldError::new(
             v.err,
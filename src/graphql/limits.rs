@@ -0,0 +1,357 @@
+use crate::graphql::errors::FieldErrorCode;
+use crate::graphql::{new_schema, Schema};
+use crate::rate_limit::OperationKind;
+use juniper::parser::parse_document_source;
+use juniper::{Definition, OperationType, Selection, Type};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Cost of a field by `(type, field)`, every other field costs 1.
+///
+/// The selection of a list field is multiplied by its cost to account for the fan-out.
+const COSTS: &[(&str, &str, usize)] = &[
+    ("Query", "me", 2),
+    ("Query", "others", 10),
+    ("Query", "allUsers", 20),
+    ("Query", "photos", 10),
+    ("Query", "photo", 2),
+    ("Query", "allPhotos", 20),
+    ("Query", "auditEvents", 20),
+    ("Mutation", "signUp", 5),
+    ("Mutation", "updateUser", 5),
+    ("Mutation", "leave", 5),
+    ("Mutation", "createPhoto", 5),
+    ("Mutation", "updatePhoto", 5),
+    ("Mutation", "deletePhoto", 5),
+    ("Mutation", "updatePhotos", 20),
+    ("Mutation", "deletePhotos", 20),
+    ("Mutation", "requestDataExport", 5),
+    ("Mutation", "importPhotos", 50),
+    ("Me", "loadPhotos", 5),
+    ("Me", "dataExports", 5),
+    ("Other", "photos", 10),
+    ("Other", "loadPhotos", 5),
+    ("OtherConnection", "edges", 10),
+    ("Photo", "loadUser", 5),
+    ("PhotoConnection", "edges", 10),
+    ("AuditEventConnection", "edges", 10),
+];
+
+/// Deepest list or object value allowed in arguments and variable types, which juniper's parser
+/// also reads recursively.
+const MAX_VALUE_NESTING: usize = 32;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum LimitError {
+    #[error("query depth {depth} exceeds the limit of {max}")]
+    TooDeep { depth: usize, max: usize },
+    #[error("query complexity {complexity} exceeds the limit of {max}")]
+    TooComplex { complexity: usize, max: usize },
+    #[error("query uses {aliases} aliases, the limit is {max}")]
+    TooManyAliases { aliases: usize, max: usize },
+    #[error("query could not be analyzed: {0}")]
+    Syntax(String),
+}
+
+impl LimitError {
+    pub fn code(&self) -> FieldErrorCode {
+        match self {
+            LimitError::TooDeep { .. } => FieldErrorCode::QueryTooDeep,
+            LimitError::TooComplex { .. } => FieldErrorCode::QueryTooComplex,
+            LimitError::TooManyAliases { .. } => FieldErrorCode::TooManyAliases,
+            LimitError::Syntax(_) => FieldErrorCode::BadRequest,
+        }
+    }
+}
+
+/// Rejects over-budget operations before they reach the executor.
+///
+/// The document is parsed with juniper's own parser and walked against the schema, field costs
+/// come from `COSTS`. The complexity of a field is its cost plus the complexity of its selection.
+///
+/// Fragments, inline or spread, count as a level of depth like a field does, so nesting them
+/// can't get around the limit. juniper's parser recurses once per level, so the nesting of the
+/// source is bounded before it is parsed at all.
+#[derive(Clone)]
+pub struct QueryLimits {
+    max_depth: usize,
+    max_complexity: usize,
+    max_aliases: usize,
+    schema: Arc<Schema>,
+    costs: HashMap<(&'static str, &'static str), usize>,
+}
+
+impl QueryLimits {
+    pub fn new(max_depth: usize, max_complexity: usize, max_aliases: usize) -> Self {
+        let schema = new_schema();
+        for (type_name, field, _) in COSTS {
+            if field_type(&schema, type_name, field).is_none() {
+                panic!(
+                    "COSTS lists {}.{}, which isn't in the schema",
+                    type_name, field
+                );
+            }
+        }
+
+        QueryLimits {
+            max_depth,
+            max_complexity,
+            max_aliases,
+            schema: Arc::new(schema),
+            costs: COSTS
+                .iter()
+                .map(|(type_name, field, cost)| ((*type_name, *field), *cost))
+                .collect(),
+        }
+    }
+
+    /// Checks the operation that will be executed and tells whether it is a query or a mutation,
+    /// from the one parse of `query`.
+    pub fn check(
+        &self,
+        query: &str,
+        operation_name: Option<&str>,
+    ) -> Result<OperationKind, LimitError> {
+        check_nesting(query, self.max_depth)?;
+        let document = parse_document_source(query, &self.schema.schema)
+            .map_err(|e| LimitError::Syntax(e.item.to_string()))?;
+        let fragments = document
+            .iter()
+            .filter_map(|v| match v {
+                Definition::Fragment(v) => Some((
+                    v.item.name.item,
+                    (v.item.type_condition.item, &v.item.selection_set[..]),
+                )),
+                Definition::Operation(_) => None,
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut kind = OperationKind::Query;
+        for operation in document.iter().filter_map(|v| match v {
+            Definition::Operation(v) => Some(&v.item),
+            Definition::Fragment(_) => None,
+        }) {
+            let name = operation.name.as_ref().map(|v| v.item);
+            if operation_name.map_or(false, |v| name != Some(v)) {
+                continue;
+            }
+
+            let root_type = match operation.operation_type {
+                OperationType::Query => "Query",
+                OperationType::Mutation => {
+                    kind = OperationKind::Mutation;
+                    "Mutation"
+                }
+                OperationType::Subscription => "Subscription",
+            };
+
+            let mut walk = Walk {
+                limits: self,
+                fragments: &fragments,
+                spreads: Vec::new(),
+                fields: 0,
+                aliases: 0,
+            };
+            let complexity = walk.selections(Some(root_type), &operation.selection_set, 1)?;
+
+            if complexity > self.max_complexity {
+                return Err(LimitError::TooComplex {
+                    complexity,
+                    max: self.max_complexity,
+                });
+            }
+            if walk.aliases > self.max_aliases {
+                return Err(LimitError::TooManyAliases {
+                    aliases: walk.aliases,
+                    max: self.max_aliases,
+                });
+            }
+        }
+
+        Ok(kind)
+    }
+}
+
+/// The type of `field` on the object or interface `type_name`.
+fn field_type<'s>(schema: &'s Schema, type_name: &str, field: &str) -> Option<&'s Type<'s>> {
+    schema
+        .schema
+        .concrete_type_by_name(type_name)?
+        .field_by_name(field)
+        .map(|v| &v.field_type)
+}
+
+/// The type condition and selections of every fragment in a document, by name.
+type Fragments<'d, 'a> = HashMap<&'a str, (&'a str, &'d [Selection<'a>])>;
+
+/// One operation, walked with the fragments of its document.
+struct Walk<'l, 'd, 'a> {
+    limits: &'l QueryLimits,
+    fragments: &'d Fragments<'d, 'a>,
+    /// Fragments being spread, to stop at cycles.
+    spreads: Vec<&'a str>,
+    fields: usize,
+    aliases: usize,
+}
+
+impl<'l, 'd, 'a> Walk<'l, 'd, 'a> {
+    fn selections(
+        &mut self,
+        parent_type: Option<&str>,
+        selections: &'d [Selection<'a>],
+        depth: usize,
+    ) -> Result<usize, LimitError> {
+        let limits = self.limits;
+        if depth > limits.max_depth {
+            return Err(LimitError::TooDeep {
+                depth,
+                max: limits.max_depth,
+            });
+        }
+
+        let mut complexity: usize = 0;
+
+        for selection in selections {
+            match selection {
+                Selection::Field(field) => {
+                    let field = &field.item;
+                    // every field adds at least 1, so this bounds the work spent on fragment bombs
+                    self.fields += 1;
+                    if self.fields > limits.max_complexity {
+                        return Err(LimitError::TooComplex {
+                            complexity: self.fields,
+                            max: limits.max_complexity,
+                        });
+                    }
+                    if field.alias.is_some() {
+                        self.aliases += 1;
+                    }
+
+                    let name = field.name.item;
+                    let cost = parent_type
+                        .and_then(|v| limits.costs.get(&(v, name)).copied())
+                        .unwrap_or(1);
+                    let field_type = parent_type.and_then(|v| field_type(&limits.schema, v, name));
+                    let multiplier = match field_type {
+                        Some(Type::List(_)) | Some(Type::NonNullList(_)) => cost,
+                        _ => 1,
+                    };
+
+                    let children = match &field.selection_set {
+                        Some(v) if !v.is_empty() => {
+                            self.selections(field_type.map(|v| v.innermost_name()), v, depth + 1)?
+                        }
+                        _ => 0,
+                    };
+
+                    complexity = complexity
+                        .saturating_add(cost)
+                        .saturating_add(multiplier.saturating_mul(children));
+                }
+                Selection::InlineFragment(fragment) => {
+                    let fragment = &fragment.item;
+                    let children = self.selections(
+                        fragment
+                            .type_condition
+                            .as_ref()
+                            .map(|v| v.item)
+                            .or(parent_type),
+                        &fragment.selection_set,
+                        depth + 1,
+                    )?;
+                    complexity = complexity.saturating_add(children);
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.item.name.item;
+                    if self.spreads.contains(&name) {
+                        return Err(LimitError::Syntax(format!(
+                            "fragment {} spreads itself",
+                            name
+                        )));
+                    }
+                    let (type_condition, selections) = *self
+                        .fragments
+                        .get(name)
+                        .ok_or_else(|| LimitError::Syntax(format!("unknown fragment {}", name)))?;
+
+                    self.spreads.push(name);
+                    let children = self.selections(Some(type_condition), selections, depth + 1)?;
+                    self.spreads.pop();
+
+                    complexity = complexity.saturating_add(children);
+                }
+            }
+        }
+
+        Ok(complexity)
+    }
+}
+
+/// Fails with `TooDeep` once selection sets nest more than `max_depth` levels, and rejects values
+/// nested deeper than `MAX_VALUE_NESTING` inside parentheses, scanning without recursion.
+fn check_nesting(source: &str, max_depth: usize) -> Result<(), LimitError> {
+    let bytes = source.as_bytes();
+    let mut selections: usize = 0;
+    let mut parentheses: usize = 0;
+    let mut values: usize = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' if bytes[i..].starts_with(b"\"\"\"") => {
+                i += 3;
+                while i < bytes.len() && !bytes[i..].starts_with(b"\"\"\"") {
+                    i += if bytes[i..].starts_with(b"\\\"\"\"") {
+                        4
+                    } else {
+                        1
+                    };
+                }
+                i += 2;
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'(' => parentheses += 1,
+            b')' => {
+                parentheses = parentheses.saturating_sub(1);
+                if parentheses == 0 {
+                    values = 0;
+                }
+            }
+            b'{' | b'[' if parentheses > 0 => {
+                values += 1;
+                if values > MAX_VALUE_NESTING {
+                    return Err(LimitError::Syntax(format!(
+                        "values nest more than {} levels",
+                        MAX_VALUE_NESTING
+                    )));
+                }
+            }
+            b'}' | b']' if parentheses > 0 => values = values.saturating_sub(1),
+            b'{' => {
+                selections += 1;
+                if selections > max_depth {
+                    return Err(LimitError::TooDeep {
+                        depth: selections,
+                        max: max_depth,
+                    });
+                }
+            }
+            b'}' => selections = selections.saturating_sub(1),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use crate::domain;
+use crate::graphql::errors::FieldErrorWithCode;
 use crate::graphql::*;
+use crate::metrics;
 use juniper_from_schema::{QueryTrail, Walked};
 
 #[derive(Debug, Clone)]
@@ -10,13 +12,25 @@ pub struct Me {
 #[async_trait]
 impl MeFields for Me {
     fn field_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
-        Ok(Into::into(self.user.id.clone()))
+        Ok(Into::into(self.user.id.to_string()))
     }
 
     fn field_name(&self, _: &Executor<Context>) -> FieldResult<String> {
         Ok(self.user.name.clone())
     }
 
+    fn field_version(&self, _: &Executor<Context>) -> FieldResult<i32> {
+        Ok(self.user.version)
+    }
+
+    fn field_created_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.user.created_at))
+    }
+
+    fn field_updated_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.user.updated_at))
+    }
+
     fn field_photos<'r>(
         &self,
         _: &Executor<Context>,
@@ -39,7 +53,8 @@ impl MeFields for Me {
     ) -> FieldResult<Vec<Photo>> {
         let ctx = exec.context();
 
-        let photos: Vec<domain::photo::Photo> = ctx.photo_loader.load(self.user.id.clone()).await?;
+        metrics::observe_loader_load("photo_loader");
+        let photos: Vec<domain::photo::Photo> = ctx.photo_loader.load(self.user.id).await?;
 
         Ok(photos
             .iter()
@@ -49,4 +64,23 @@ impl MeFields for Me {
             })
             .collect::<Vec<_>>())
     }
+
+    async fn field_data_exports<'s, 'r, 'a>(
+        &'s self,
+        exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, DataExport, Walked>,
+    ) -> FieldResult<Vec<DataExport>> {
+        let ctx = exec.context();
+
+        let exports = ctx
+            .repositories
+            .data_exports
+            .get_all_by_user(self.user.id)
+            .map_err(FieldErrorWithCode::from)?;
+
+        Ok(exports
+            .into_iter()
+            .map(|export| DataExport { export })
+            .collect::<Vec<_>>())
+    }
 }
@@ -1,15 +1,21 @@
-use crate::ddb::{DaoError, Tx};
+use crate::ddb::DaoError;
 use crate::domain;
+use crate::domain::audit_event::Change;
+use crate::graphql::data_export::DataExport;
 use crate::graphql::errors::FieldErrorWithCode;
 use crate::graphql::me::Me;
-use crate::graphql::photo::Photo;
+use crate::graphql::photo::{ImportPhotosReport, Photo, PhotoBatchResult};
 use crate::graphql::Context;
 use crate::graphql::*;
+use crate::import::{self, ImportRow};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use juniper::{Executor, FieldResult};
 use juniper_from_schema::{QueryTrail, Walked};
 
+/// Most ids accepted by one batch mutation.
+const MAX_BATCH_IDS: usize = 1000;
+
 pub struct Mutation;
 
 #[async_trait]
@@ -21,21 +27,21 @@ impl MutationFields for Mutation {
         input: SignUpInput,
     ) -> FieldResult<Me> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let user_dao = ctx.ddb_dao::<domain::user::User>();
 
-        let now: DateTime<Utc> = Utc::now();
         let name: String = input.name;
 
         if name.is_empty() {
             return Err(FieldErrorWithCode::bad_request().into());
         }
 
-        let user = domain::user::User::new(name, now);
+        let user = domain::user::User::new(name, ctx.ids.as_ref(), ctx.clock.as_ref());
 
-        if let Err(e) = user_dao.insert(&conn, &user) {
-            return Err(FieldErrorWithCode::from(e).into());
-        }
+        ctx.repositories
+            .transaction(|| {
+                ctx.repositories.users.insert(&user)?;
+                ctx.audit(user.id, "signUp", vec![Change::Created(&user)])
+            })
+            .map_err(FieldErrorWithCode::from)?;
 
         Ok(Me {
             user,
@@ -50,30 +56,42 @@ impl MutationFields for Mutation {
         input: UpdateUserInput,
     ) -> FieldResult<Me> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let user_dao = ctx.ddb_dao::<domain::user::User>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let now: DateTime<Utc> = Utc::now();
+        let now: DateTime<Utc> = ctx.clock.now();
         let name: String = input.name;
+        let expected_version = input.expected_version;
 
         if name.is_empty() {
             return Err(FieldErrorWithCode::bad_request().into());
         }
 
-        let user = Tx::run(&conn, || {
-            let mut user = user_dao.get(&conn, authorized_user_id)?;
-
-            user.update(name, now);
-
-            user_dao.update(&conn, &user)?;
-
-            Ok(user)
-        })
-        .map_err(FieldErrorWithCode::from)?;
+        let user = ctx
+            .repositories
+            .transaction(|| {
+                let mut user = ctx.repositories.users.get(authorized_user_id)?;
+                if expected_version.map_or(false, |v| v != user.version) {
+                    return Err(DaoError::Conflict);
+                }
+
+                let before = user.clone();
+                user.update(name, now);
+
+                ctx.repositories.users.update(&user)?;
+                ctx.audit(
+                    authorized_user_id,
+                    "updateUser",
+                    vec![Change::Updated {
+                        before: &before,
+                        after: &user,
+                    }],
+                )?;
+
+                Ok(user)
+            })
+            .map_err(FieldErrorWithCode::from)?;
 
         Ok(Me {
             user,
@@ -86,16 +104,26 @@ impl MutationFields for Mutation {
         exec: &Executor<'r, 'a, Context>,
     ) -> FieldResult<bool> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let user_dao = ctx.ddb_dao::<domain::user::User>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        if let Err(e) = user_dao.delete(&conn, authorized_user_id) {
-            return Err(FieldErrorWithCode::from(e).into());
-        }
+        ctx.repositories
+            .transaction(|| {
+                // leaving twice isn't an error, but only the first time is recorded
+                let user = match ctx.repositories.users.get(authorized_user_id) {
+                    Ok(v) => Some(v),
+                    Err(DaoError::NotFound) => None,
+                    Err(e) => return Err(e),
+                };
+                ctx.repositories.users.delete(authorized_user_id)?;
+                ctx.audit(
+                    authorized_user_id,
+                    "leave",
+                    user.iter().map(Change::Deleted).collect(),
+                )
+            })
+            .map_err(FieldErrorWithCode::from)?;
 
         Ok(true)
     }
@@ -107,14 +135,10 @@ impl MutationFields for Mutation {
         input: CreatePhotoInput,
     ) -> FieldResult<Photo> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let now: DateTime<Utc> = Utc::now();
         let url: String = input.url;
         let is_public = input.is_public;
 
@@ -122,11 +146,24 @@ impl MutationFields for Mutation {
             return Err(FieldErrorWithCode::bad_request().into());
         }
 
-        let photo = domain::photo::Photo::new(authorized_user_id, url, is_public, now);
-
-        if let Err(e) = photo_dao.insert(&conn, &photo) {
-            return Err(FieldErrorWithCode::from(e).into());
-        }
+        let photo = domain::photo::Photo::new(
+            authorized_user_id,
+            url,
+            is_public,
+            ctx.ids.as_ref(),
+            ctx.clock.as_ref(),
+        );
+
+        ctx.repositories
+            .transaction(|| {
+                ctx.repositories.photos.insert(&photo)?;
+                ctx.audit(
+                    authorized_user_id,
+                    "createPhoto",
+                    vec![Change::Created(&photo)],
+                )
+            })
+            .map_err(FieldErrorWithCode::from)?;
 
         Ok(Photo { photo, user: None })
     }
@@ -138,30 +175,42 @@ impl MutationFields for Mutation {
         input: UpdatePhotoInput,
     ) -> FieldResult<Photo> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let now: DateTime<Utc> = Utc::now();
-        let id = input.id;
+        let now: DateTime<Utc> = ctx.clock.now();
+        let id = input.id.parse()?;
         let is_public = input.is_public;
-
-        let photo = Tx::run(&conn, || {
-            let mut photo = photo_dao.get(&conn, id.clone())?;
-            if photo.user_id != authorized_user_id {
-                return Err(DaoError::Forbidden);
-            }
-
-            photo.update_visibility(is_public, now);
-
-            photo_dao.update(&conn, &photo)?;
-
-            Ok(photo)
-        })
-        .map_err(FieldErrorWithCode::from)?;
+        let expected_version = input.expected_version;
+
+        let photo = ctx
+            .repositories
+            .transaction(|| {
+                let mut photo = ctx.repositories.photos.get(id)?;
+                if photo.user_id != authorized_user_id {
+                    return Err(DaoError::Forbidden);
+                }
+                if expected_version.map_or(false, |v| v != photo.version) {
+                    return Err(DaoError::Conflict);
+                }
+
+                let before = photo.clone();
+                photo.update_visibility(is_public, now);
+
+                ctx.repositories.photos.update(&photo)?;
+                ctx.audit(
+                    authorized_user_id,
+                    "updatePhoto",
+                    vec![Change::Updated {
+                        before: &before,
+                        after: &photo,
+                    }],
+                )?;
+
+                Ok(photo)
+            })
+            .map_err(FieldErrorWithCode::from)?;
 
         Ok(Photo { photo, user: None })
     }
@@ -172,26 +221,222 @@ impl MutationFields for Mutation {
         input: DeletePhotoInput,
     ) -> FieldResult<bool> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let id = input.id;
+        let id = input.id.parse()?;
+
+        ctx.repositories
+            .transaction(|| {
+                let photo = ctx.repositories.photos.get(id)?;
+                if photo.user_id != authorized_user_id {
+                    return Err(DaoError::Forbidden);
+                }
+
+                ctx.repositories.photos.delete(id)?;
+                ctx.audit(
+                    authorized_user_id,
+                    "deletePhoto",
+                    vec![Change::Deleted(&photo)],
+                )
+            })
+            .map_err(FieldErrorWithCode::from)?;
+
+        Ok(true)
+    }
+
+    async fn field_update_photos<'s, 'r, 'a>(
+        &'s self,
+        exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, PhotoBatchResult, Walked>,
+        ids: Vec<PhotoId>,
+        is_public: bool,
+    ) -> FieldResult<PhotoBatchResult> {
+        let ctx = exec.context();
+        let authorized_user_id = ctx
+            .authorized_user_id
+            .ok_or(FieldErrorWithCode::un_authenticate())?;
+
+        if ids.len() > MAX_BATCH_IDS {
+            return Err(FieldErrorWithCode::bad_request().into());
+        }
 
-        let photo = photo_dao
-            .get(&conn, id.clone())
+        let now: DateTime<Utc> = ctx.clock.now();
+        let ids = ids
+            .iter()
+            .map(PhotoId::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let result = ctx
+            .repositories
+            .transaction(|| {
+                let owners = ctx.repositories.photos.get_owners_for_update(ids.clone())?;
+                let result = PhotoBatchResult::classify(ids.clone(), &owners, authorized_user_id);
+                if !result.succeeded.is_empty() {
+                    let before = ctx.repositories.photos.get_all(result.succeeded.clone())?;
+                    ctx.repositories.photos.update_visibility_all(
+                        result.succeeded.clone(),
+                        is_public,
+                        now.naive_utc(),
+                    )?;
+
+                    let after = before
+                        .iter()
+                        .cloned()
+                        .map(|mut v| {
+                            v.update_visibility(is_public, now);
+                            v
+                        })
+                        .collect::<Vec<_>>();
+                    ctx.audit(
+                        authorized_user_id,
+                        "updatePhotos",
+                        before
+                            .iter()
+                            .zip(&after)
+                            .map(|(before, after)| Change::Updated { before, after })
+                            .collect(),
+                    )?;
+                }
+                Ok(result)
+            })
             .map_err(FieldErrorWithCode::from)?;
-        if photo.user_id != authorized_user_id {
-            return Err(FieldErrorWithCode::forbidden().into());
+
+        Ok(result)
+    }
+
+    async fn field_delete_photos<'s, 'r, 'a>(
+        &'s self,
+        exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, PhotoBatchResult, Walked>,
+        ids: Vec<PhotoId>,
+    ) -> FieldResult<PhotoBatchResult> {
+        let ctx = exec.context();
+        let authorized_user_id = ctx
+            .authorized_user_id
+            .ok_or(FieldErrorWithCode::un_authenticate())?;
+
+        if ids.len() > MAX_BATCH_IDS {
+            return Err(FieldErrorWithCode::bad_request().into());
         }
 
-        if let Err(e) = photo_dao.delete(&conn, id.clone()) {
-            return Err(FieldErrorWithCode::from(e).into());
+        let ids = ids
+            .iter()
+            .map(PhotoId::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let result = ctx
+            .repositories
+            .transaction(|| {
+                let owners = ctx.repositories.photos.get_owners_for_update(ids.clone())?;
+                let result = PhotoBatchResult::classify(ids.clone(), &owners, authorized_user_id);
+                if !result.succeeded.is_empty() {
+                    let photos = ctx.repositories.photos.get_all(result.succeeded.clone())?;
+                    ctx.repositories
+                        .photos
+                        .delete_all(result.succeeded.clone())?;
+                    ctx.audit(
+                        authorized_user_id,
+                        "deletePhotos",
+                        photos.iter().map(Change::Deleted).collect(),
+                    )?;
+                }
+                Ok(result)
+            })
+            .map_err(FieldErrorWithCode::from)?;
+
+        Ok(result)
+    }
+
+    async fn field_request_data_export<'s, 'r, 'a>(
+        &'s self,
+        exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, DataExport, Walked>,
+    ) -> FieldResult<DataExport> {
+        let ctx = exec.context();
+        let authorized_user_id = ctx
+            .authorized_user_id
+            .ok_or(FieldErrorWithCode::un_authenticate())?;
+
+        let export = domain::data_export::DataExport::new(
+            authorized_user_id,
+            ctx.ids.as_ref(),
+            ctx.clock.as_ref(),
+        );
+
+        ctx.repositories
+            .transaction(|| {
+                // one archive at a time per user, the previous one is still being built
+                let exports = ctx
+                    .repositories
+                    .data_exports
+                    .get_all_by_user(authorized_user_id)?;
+                if exports.iter().any(|v| v.status.is_active()) {
+                    return Err(DaoError::Conflict);
+                }
+                ctx.repositories.data_exports.insert(&export)?;
+                ctx.audit(
+                    authorized_user_id,
+                    "requestDataExport",
+                    vec![Change::Created(&export)],
+                )
+            })
+            .map_err(FieldErrorWithCode::from)?;
+
+        ctx.exporter.spawn(export.id.clone());
+
+        Ok(DataExport { export })
+    }
+
+    async fn field_import_photos<'s, 'r, 'a>(
+        &'s self,
+        exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, ImportPhotosReport, Walked>,
+        input: Vec<CreatePhotoInput>,
+    ) -> FieldResult<ImportPhotosReport> {
+        let ctx = exec.context();
+        let authorized_user_id = ctx
+            .authorized_user_id
+            .ok_or(FieldErrorWithCode::un_authenticate())?;
+
+        if input.len() > import::MAX_ROWS_PER_REQUEST {
+            return Err(FieldErrorWithCode::bad_request().into());
         }
 
-        Ok(true)
+        let rows = input
+            .into_iter()
+            .map(|v| ImportRow {
+                url: v.url,
+                is_public: v.is_public,
+            })
+            .collect::<Vec<_>>();
+
+        // the import's own transaction is nested in this one, so the photos and their events
+        // are committed together
+        let results = ctx
+            .repositories
+            .transaction(|| {
+                let results = import::import_photos(
+                    &ctx.repositories,
+                    authorized_user_id,
+                    rows,
+                    ctx.ids.as_ref(),
+                    ctx.clock.as_ref(),
+                )?;
+                ctx.audit(
+                    authorized_user_id,
+                    "importPhotos",
+                    results
+                        .iter()
+                        .filter_map(|v| v.result.as_ref().ok())
+                        .map(Change::Created)
+                        .collect(),
+                )?;
+                Ok(results)
+            })
+            .map_err(FieldErrorWithCode::from)?;
+
+        Ok(ImportPhotosReport(results))
     }
 }
@@ -1,5 +1,6 @@
 use crate::domain;
 use crate::graphql::*;
+use crate::metrics;
 use errors::*;
 use juniper_from_schema::{QueryTrail, Walked};
 
@@ -11,21 +12,27 @@ pub struct Other {
 #[async_trait]
 impl OtherFields for Other {
     fn field_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
-        Ok(Into::into(self.user.id.clone()))
+        Ok(Into::into(self.user.id.to_string()))
     }
 
     fn field_name(&self, _: &Executor<Context>) -> FieldResult<String> {
         Ok(self.user.name.clone())
     }
 
+    fn field_created_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.user.created_at))
+    }
+
+    fn field_updated_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.user.updated_at))
+    }
+
     async fn field_photos<'s, 'r, 'a>(
         &'s self,
         exec: &Executor<'r, 'a, Context>,
         _: &QueryTrail<'r, Photo, Walked>,
     ) -> FieldResult<Vec<Photo>> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
 
         if let Some(photos) = self.photos.clone() {
             return Ok(photos
@@ -37,8 +44,10 @@ impl OtherFields for Other {
                 .collect::<Vec<_>>());
         }
 
-        let photos = photo_dao
-            .get_all_by_user(&conn, self.user.id.clone())
+        let photos = ctx
+            .repositories
+            .photos
+            .get_all_by_user(self.user.id)
             .map_err(FieldErrorWithCode::from)?;
 
         Ok(photos
@@ -57,7 +66,8 @@ impl OtherFields for Other {
     ) -> FieldResult<Vec<Photo>> {
         let ctx = exec.context();
 
-        let photos: Vec<domain::photo::Photo> = ctx.photo_loader.load(self.user.id.clone()).await?;
+        metrics::observe_loader_load("photo_loader");
+        let photos: Vec<domain::photo::Photo> = ctx.photo_loader.load(self.user.id).await?;
 
         Ok(photos
             .iter()
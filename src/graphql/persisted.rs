@@ -0,0 +1,123 @@
+use crate::graphql::errors::FieldErrorCode;
+use crate::graphql::request::Payload;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum PersistedQueryError {
+    // Apollo clients match on this exact message to resend the full query
+    #[error("PersistedQueryNotFound")]
+    NotFound,
+    #[error("operation is not in the allowlist")]
+    NotAllowed,
+    #[error("persisted query version {0} is not supported")]
+    UnsupportedVersion(i32),
+    #[error("provided sha256Hash does not match the query")]
+    HashMismatch,
+    #[error("query is missing")]
+    MissingQuery,
+}
+
+impl PersistedQueryError {
+    pub fn code(&self) -> FieldErrorCode {
+        match self {
+            PersistedQueryError::NotFound => FieldErrorCode::PersistedQueryNotFound,
+            PersistedQueryError::NotAllowed => FieldErrorCode::PersistedQueryNotAllowed,
+            _ => FieldErrorCode::BadRequest,
+        }
+    }
+}
+
+/// Resolves Apollo-style automatic persisted queries.
+///
+/// Queries are remembered in an LRU cache keyed by their SHA-256 hash.
+/// With an allowlist only the operations loaded at startup can be executed,
+/// either by hash or by sending the exact same document.
+pub struct PersistedQueries {
+    cache: Mutex<LruCache<String, String>>,
+    allowlist: Option<HashMap<String, String>>,
+}
+
+impl PersistedQueries {
+    pub fn new(cache_size: usize) -> Self {
+        PersistedQueries {
+            cache: Mutex::new(LruCache::new(cache_size)),
+            allowlist: None,
+        }
+    }
+
+    pub fn with_allowlist(mut self, dir: &Path) -> io::Result<Self> {
+        let mut allowlist = HashMap::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |v| v != "graphql") {
+                continue;
+            }
+
+            let query = fs::read_to_string(&path)?;
+            allowlist.insert(hash(&query), query);
+        }
+
+        self.allowlist = Some(allowlist);
+        Ok(self)
+    }
+
+    pub fn resolve(&self, payload: &mut Payload) -> Result<(), PersistedQueryError> {
+        let persisted_query = payload
+            .extensions
+            .as_ref()
+            .and_then(|v| v.persisted_query.as_ref());
+
+        let expected_hash = match persisted_query {
+            Some(v) if v.version != 1 => {
+                return Err(PersistedQueryError::UnsupportedVersion(v.version))
+            }
+            Some(v) => Some(v.sha256_hash.clone()),
+            None => None,
+        };
+
+        if let Some(allowlist) = &self.allowlist {
+            let key = match (payload.query.as_deref(), expected_hash) {
+                (Some(query), _) => hash(query),
+                (None, Some(v)) => v,
+                (None, None) => return Err(PersistedQueryError::MissingQuery),
+            };
+            let query = allowlist.get(&key).ok_or(PersistedQueryError::NotAllowed)?;
+            payload.query = Some(query.clone());
+            return Ok(());
+        }
+
+        let query = match (payload.query.take(), expected_hash) {
+            (Some(query), Some(expected_hash)) => {
+                if hash(&query) != expected_hash {
+                    return Err(PersistedQueryError::HashMismatch);
+                }
+                self.cache.lock().unwrap().put(expected_hash, query.clone());
+                query
+            }
+            (None, Some(expected_hash)) => self
+                .cache
+                .lock()
+                .unwrap()
+                .get(&expected_hash)
+                .cloned()
+                .ok_or(PersistedQueryError::NotFound)?,
+            (Some(query), None) => query,
+            (None, None) => return Err(PersistedQueryError::MissingQuery),
+        };
+        payload.query = Some(query);
+
+        Ok(())
+    }
+}
+
+fn hash(query: &str) -> String {
+    hex::encode(Sha256::digest(query.as_bytes()))
+}
@@ -1,7 +1,10 @@
 use crate::domain;
 use crate::graphql::errors::FieldErrorWithCode;
 use crate::graphql::*;
+use crate::import::RowResult;
+use crate::metrics;
 use juniper_from_schema::{QueryTrail, Walked};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub struct Photo {
@@ -11,11 +14,11 @@ pub struct Photo {
 #[async_trait]
 impl PhotoFields for Photo {
     fn field_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
-        Ok(Into::into(self.photo.id.clone()))
+        Ok(Into::into(self.photo.id.to_string()))
     }
 
-    fn field_user_id(&self, _: &Executor<Context>) -> FieldResult<ID> {
-        Ok(Into::into(self.photo.user_id.clone()))
+    fn field_user_id(&self, _: &Executor<Context>) -> FieldResult<UserId> {
+        Ok(self.photo.user_id.into())
     }
 
     fn field_url(&self, _: &Executor<Context>) -> FieldResult<String> {
@@ -26,6 +29,18 @@ impl PhotoFields for Photo {
         Ok(self.photo.is_public)
     }
 
+    fn field_version(&self, _: &Executor<Context>) -> FieldResult<i32> {
+        Ok(self.photo.version)
+    }
+
+    fn field_created_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.photo.created_at))
+    }
+
+    fn field_updated_at(&self, _: &Executor<Context>) -> FieldResult<DateTime<Utc>> {
+        Ok(utc(self.photo.updated_at))
+    }
+
     fn field_user<'r>(
         &self,
         _: &Executor<Context>,
@@ -48,8 +63,8 @@ impl PhotoFields for Photo {
     ) -> FieldResult<Other> {
         let ctx = exec.context();
 
-        let users: Vec<domain::user::User> =
-            ctx.user_loader.load(self.photo.user_id.clone()).await?;
+        metrics::observe_loader_load("user_loader");
+        let users: Vec<domain::user::User> = ctx.user_loader.load(self.photo.user_id).await?;
         if users.is_empty() {
             return Err(FieldErrorWithCode::not_found().into());
         }
@@ -100,3 +115,92 @@ impl PhotoConnectionFields for PhotoConnection {
         Ok(edges)
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct ImportPhotosReport(pub Vec<RowResult>);
+impl ImportPhotosReportFields for ImportPhotosReport {
+    fn field_imported(&self, _: &Executor<Context>) -> FieldResult<i32> {
+        Ok(self.0.iter().filter(|v| v.result.is_ok()).count() as i32)
+    }
+
+    fn field_failed(&self, _: &Executor<Context>) -> FieldResult<i32> {
+        Ok(self.0.iter().filter(|v| v.result.is_err()).count() as i32)
+    }
+
+    fn field_rows<'r>(
+        &self,
+        _: &Executor<Context>,
+        _: &QueryTrail<'r, ImportPhotoRow, Walked>,
+    ) -> FieldResult<Vec<ImportPhotoRow>> {
+        Ok(self
+            .0
+            .iter()
+            .cloned()
+            .map(ImportPhotoRow)
+            .collect::<Vec<_>>())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportPhotoRow(pub RowResult);
+impl ImportPhotoRowFields for ImportPhotoRow {
+    fn field_index(&self, _: &Executor<Context>) -> FieldResult<i32> {
+        Ok(self.0.index as i32)
+    }
+
+    fn field_photo<'r>(
+        &self,
+        _: &Executor<Context>,
+        _: &QueryTrail<'r, Photo, Walked>,
+    ) -> FieldResult<Option<Photo>> {
+        Ok(self.0.result.as_ref().ok().map(|v| Photo {
+            photo: v.to_owned(),
+            user: None,
+        }))
+    }
+
+    fn field_error(&self, _: &Executor<Context>) -> FieldResult<Option<String>> {
+        Ok(self.0.result.as_ref().err().cloned())
+    }
+}
+
+/// Outcome of a batch mutation, every requested id lands in exactly one list.
+#[derive(Debug, Clone, Default)]
+pub struct PhotoBatchResult {
+    pub succeeded: Vec<domain::id::PhotoId>,
+    pub forbidden: Vec<domain::id::PhotoId>,
+    pub not_found: Vec<domain::id::PhotoId>,
+}
+impl PhotoBatchResultFields for PhotoBatchResult {
+    fn field_succeeded(&self, _: &Executor<Context>) -> FieldResult<Vec<PhotoId>> {
+        Ok(self.succeeded.iter().copied().map(Into::into).collect())
+    }
+
+    fn field_forbidden(&self, _: &Executor<Context>) -> FieldResult<Vec<PhotoId>> {
+        Ok(self.forbidden.iter().copied().map(Into::into).collect())
+    }
+
+    fn field_not_found(&self, _: &Executor<Context>) -> FieldResult<Vec<PhotoId>> {
+        Ok(self.not_found.iter().copied().map(Into::into).collect())
+    }
+}
+
+impl PhotoBatchResult {
+    /// Sorts `ids` by the owners in `owners`, keeping the request order and dropping duplicates.
+    pub fn classify(
+        ids: Vec<domain::id::PhotoId>,
+        owners: &HashMap<domain::id::PhotoId, domain::id::UserId>,
+        authorized_user_id: domain::id::UserId,
+    ) -> Self {
+        let mut seen = HashSet::new();
+        let mut result = PhotoBatchResult::default();
+        for id in ids.into_iter().filter(|v| seen.insert(*v)) {
+            match owners.get(&id) {
+                Some(owner) if *owner == authorized_user_id => result.succeeded.push(id),
+                Some(_) => result.forbidden.push(id),
+                None => result.not_found.push(id),
+            }
+        }
+        result
+    }
+}
@@ -1,4 +1,5 @@
-use crate::domain;
+use crate::ddb::{self, OrderBy};
+use crate::graphql::audit_event::*;
 use crate::graphql::me::*;
 use crate::graphql::other::*;
 use crate::graphql::photo::*;
@@ -19,15 +20,14 @@ impl QueryFields for Query {
         _: &QueryTrail<'r, Me, Walked>,
     ) -> FieldResult<Me> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let user_dao = ctx.ddb_dao::<domain::user::User>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let (user, photos) = user_dao
-            .get_with_photos(&conn, authorized_user_id)
+        let (user, photos) = ctx
+            .repositories
+            .users
+            .get_with_photos(authorized_user_id)
             .map_err(FieldErrorWithCode::from)?;
 
         Ok(Me { user, photos })
@@ -37,17 +37,23 @@ impl QueryFields for Query {
         &'s self,
         exec: &Executor<'r, 'a, Context>,
         _: &QueryTrail<'r, OtherConnection, Walked>,
+        filter: Option<UserFilter>,
+        order_by: Option<UserOrderBy>,
     ) -> FieldResult<OtherConnection> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let user_dao = ctx.ddb_dao::<domain::user::User>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let others = user_dao
-            .get_all_with_exclude(&conn, authorized_user_id)
+        let filter = ddb::user::Filter {
+            exclude_id: Some(authorized_user_id),
+            name_contains: filter.and_then(|v| v.name_contains),
+        };
+
+        let others = ctx
+            .repositories
+            .users
+            .find(&filter, user_order_by(order_by))
             .map(|v| v.iter().map(|v| (v.to_owned(), None)).collect::<Vec<_>>())
             .map_err(FieldErrorWithCode::from)?;
 
@@ -60,15 +66,14 @@ impl QueryFields for Query {
         _: &QueryTrail<'r, OtherConnection, Walked>,
     ) -> FieldResult<OtherConnection> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let user_dao = ctx.ddb_dao::<domain::user::User>();
         let _authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let others = user_dao
-            .get_all_with_photos(&conn)
+        let others = ctx
+            .repositories
+            .users
+            .get_all_with_photos()
             .map(|v| {
                 v.iter()
                     .map(|v| (v.0.to_owned(), Some(v.1.to_owned())))
@@ -83,17 +88,23 @@ impl QueryFields for Query {
         &'s self,
         exec: &Executor<'r, 'a, Context>,
         _: &QueryTrail<'r, PhotoConnection, Walked>,
+        filter: Option<PhotoFilter>,
+        order_by: Option<PhotoOrderBy>,
     ) -> FieldResult<PhotoConnection> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let photos = photo_dao
-            .get_all_by_user(&conn, authorized_user_id)
+        let filter = ddb::photo::Filter {
+            user_id: Some(authorized_user_id),
+            ..photo_filter(filter)?
+        };
+
+        let photos = ctx
+            .repositories
+            .photos
+            .find(&filter, photo_order_by(order_by))
             .map_err(FieldErrorWithCode::from)?;
 
         Ok(PhotoConnection(
@@ -108,18 +119,17 @@ impl QueryFields for Query {
         &'s self,
         exec: &Executor<'r, 'a, Context>,
         _: &QueryTrail<'r, Photo, Walked>,
-        id: String,
+        id: PhotoId,
     ) -> FieldResult<Photo> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
         let authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let photo = photo_dao
-            .get(&conn, id.clone())
+        let photo = ctx
+            .repositories
+            .photos
+            .get(id.parse()?)
             .map_err(FieldErrorWithCode::from)?;
         if photo.user_id != authorized_user_id {
             return Err(FieldErrorWithCode::forbidden().into());
@@ -132,17 +142,18 @@ impl QueryFields for Query {
         &'s self,
         exec: &Executor<'r, 'a, Context>,
         _: &QueryTrail<'r, PhotoConnection, Walked>,
+        filter: Option<PhotoFilter>,
+        order_by: Option<PhotoOrderBy>,
     ) -> FieldResult<PhotoConnection> {
         let ctx = exec.context();
-        let conn = ctx.get_mutex_connection();
-        let photo_dao = ctx.ddb_dao::<domain::photo::Photo>();
         let _authorized_user_id = ctx
             .authorized_user_id
-            .clone()
             .ok_or(FieldErrorWithCode::un_authenticate())?;
 
-        let photos = photo_dao
-            .get_all_with_user(&conn)
+        let photos = ctx
+            .repositories
+            .photos
+            .find_with_user(&photo_filter(filter)?, photo_order_by(order_by))
             .map_err(FieldErrorWithCode::from)?;
 
         Ok(PhotoConnection(
@@ -152,4 +163,123 @@ impl QueryFields for Query {
                 .collect::<Vec<_>>(),
         ))
     }
+
+    async fn field_audit_events<'s, 'r, 'a>(
+        &'s self,
+        exec: &Executor<'r, 'a, Context>,
+        _: &QueryTrail<'r, AuditEventConnection, Walked>,
+        filter: Option<AuditEventFilter>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<AuditEventConnection> {
+        let ctx = exec.context();
+        let _authorized_user_id = ctx
+            .authorized_user_id
+            .ok_or(FieldErrorWithCode::un_authenticate())?;
+        if !ctx.is_admin {
+            return Err(FieldErrorWithCode::forbidden().into());
+        }
+
+        let first = match first {
+            None => ddb::audit_event::FIND_LIMIT,
+            Some(v) if v >= 1 && v as usize <= ddb::audit_event::FIND_LIMIT => v as usize,
+            Some(_) => return Err(FieldErrorWithCode::bad_request().into()),
+        };
+        let after = match after {
+            Some(v) => {
+                Some(ddb::audit_event::Cursor::decode(&v).ok_or(FieldErrorWithCode::bad_request())?)
+            }
+            None => None,
+        };
+
+        // One more than asked for tells whether another page follows.
+        let mut events = ctx
+            .repositories
+            .audit_events
+            .find(&audit_event_filter(filter)?, after.as_ref(), first + 1)
+            .map_err(FieldErrorWithCode::from)?;
+        let has_next_page = events.len() > first;
+        events.truncate(first);
+
+        Ok(AuditEventConnection {
+            events,
+            has_next_page,
+        })
+    }
+}
+
+fn audit_event_filter(
+    filter: Option<AuditEventFilter>,
+) -> Result<ddb::audit_event::Filter, FieldErrorWithCode> {
+    let filter = match filter {
+        Some(v) => v,
+        None => return Ok(ddb::audit_event::Filter::default()),
+    };
+
+    let actor_id = match filter.actor_id {
+        Some(v) => Some(v.parse()?),
+        None => None,
+    };
+
+    Ok(ddb::audit_event::Filter {
+        actor_id,
+        action: filter.action,
+        entity_type: filter.entity_type,
+        entity_id: filter.entity_id.map(|v| v.to_string()),
+        request_id: filter.request_id,
+        created_after: filter.created_after.map(|v| v.naive_utc()),
+        created_before: filter.created_before.map(|v| v.naive_utc()),
+    })
+}
+
+fn photo_filter(filter: Option<PhotoFilter>) -> Result<ddb::photo::Filter, FieldErrorWithCode> {
+    let filter = match filter {
+        Some(v) => v,
+        None => return Ok(ddb::photo::Filter::default()),
+    };
+
+    let user_id = match filter.user_id {
+        Some(v) => Some(v.parse()?),
+        None => None,
+    };
+
+    Ok(ddb::photo::Filter {
+        is_public: filter.is_public,
+        user_id,
+        created_after: filter.created_after.map(|v| v.naive_utc()),
+        created_before: filter.created_before.map(|v| v.naive_utc()),
+    })
+}
+
+fn photo_order_by(order_by: Option<PhotoOrderBy>) -> OrderBy<ddb::photo::OrderField> {
+    match order_by {
+        Some(v) => OrderBy {
+            field: match v.field {
+                PhotoOrderField::CreatedAt => ddb::photo::OrderField::CreatedAt,
+                PhotoOrderField::UpdatedAt => ddb::photo::OrderField::UpdatedAt,
+            },
+            direction: sort_direction(v.direction),
+        },
+        None => OrderBy::default(),
+    }
+}
+
+fn user_order_by(order_by: Option<UserOrderBy>) -> OrderBy<ddb::user::OrderField> {
+    match order_by {
+        Some(v) => OrderBy {
+            field: match v.field {
+                UserOrderField::CreatedAt => ddb::user::OrderField::CreatedAt,
+                UserOrderField::Name => ddb::user::OrderField::Name,
+            },
+            direction: sort_direction(v.direction),
+        },
+        None => OrderBy::default(),
+    }
+}
+
+fn sort_direction(v: SortDirection) -> ddb::SortDirection {
+    match v {
+        SortDirection::Asc => ddb::SortDirection::Asc,
+        SortDirection::Desc => ddb::SortDirection::Desc,
+    }
 }
@@ -0,0 +1,113 @@
+use juniper::http::{GraphQLBatchRequest, GraphQLRequest};
+use juniper::InputValue;
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Payload {
+    pub query: Option<String>,
+    pub operation_name: Option<String>,
+    pub variables: Option<InputValue>,
+    pub extensions: Option<Extensions>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Extensions {
+    pub persisted_query: Option<PersistedQueryExtension>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedQueryExtension {
+    pub version: i32,
+    pub sha256_hash: String,
+}
+
+impl Payload {
+    pub fn from_query(query: String) -> Self {
+        Payload {
+            query: Some(query),
+            operation_name: None,
+            variables: None,
+            extensions: None,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        self.query.as_deref().unwrap_or_default()
+    }
+
+    fn into_request(self) -> GraphQLRequest {
+        GraphQLRequest::new(
+            self.query.unwrap_or_default(),
+            self.operation_name,
+            self.variables,
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum BatchPayload {
+    Single(Payload),
+    Batch(Vec<Payload>),
+}
+
+impl BatchPayload {
+    pub fn payloads(&self) -> Vec<&Payload> {
+        match self {
+            BatchPayload::Single(v) => vec![v],
+            BatchPayload::Batch(v) => v.iter().collect(),
+        }
+    }
+
+    pub fn payloads_mut(&mut self) -> Vec<&mut Payload> {
+        match self {
+            BatchPayload::Single(v) => vec![v],
+            BatchPayload::Batch(v) => v.iter_mut().collect(),
+        }
+    }
+
+    pub fn into_request(self) -> GraphQLBatchRequest {
+        match self {
+            BatchPayload::Single(v) => GraphQLBatchRequest::Single(v.into_request()),
+            BatchPayload::Batch(v) => {
+                GraphQLBatchRequest::Batch(v.into_iter().map(Payload::into_request).collect())
+            }
+        }
+    }
+}
+
+/// Query string form of a request, where `variables` and `extensions` arrive as encoded JSON.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPayload {
+    pub query: Option<String>,
+    pub operation_name: Option<String>,
+    pub variables: Option<String>,
+    pub extensions: Option<String>,
+}
+
+impl TryFrom<GetPayload> for Payload {
+    type Error = serde_json::Error;
+
+    fn try_from(v: GetPayload) -> Result<Self, Self::Error> {
+        let variables = match v.variables {
+            Some(json) => Some(serde_json::from_str(&json)?),
+            None => None,
+        };
+        let extensions = match v.extensions {
+            Some(json) => Some(serde_json::from_str(&json)?),
+            None => None,
+        };
+
+        Ok(Payload {
+            query: v.query,
+            operation_name: v.operation_name,
+            variables,
+            extensions,
+        })
+    }
+}
@@ -0,0 +1,137 @@
+use crate::config::HealthConfig;
+use crate::ddb;
+use actix_web::rt::time;
+use actix_web::web;
+use serde::Serialize;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Component {
+    pub status: Status,
+    #[serde(flatten)]
+    pub details: serde_json::Value,
+}
+
+impl Component {
+    fn up(details: serde_json::Value) -> Self {
+        Component {
+            status: Status::Up,
+            details,
+        }
+    }
+
+    fn down(details: serde_json::Value) -> Self {
+        Component {
+            status: Status::Down,
+            details,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub status: Status,
+    pub components: Components,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Components {
+    pub database: Component,
+    pub migrations: Component,
+    pub pool: Component,
+}
+
+impl Report {
+    pub fn is_up(&self) -> bool {
+        self.status == Status::Up
+    }
+}
+
+/// Checks the pool, then pings the database and looks for pending migrations on one connection.
+pub async fn check_ready(pool: &ddb::Pool, config: &HealthConfig) -> Report {
+    let pool_component = check_pool(pool, config.max_pool_utilisation);
+
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let started_at = Instant::now();
+    let pool = pool.clone();
+    let probe = time::timeout(
+        timeout,
+        web::block(move || -> Result<_, String> {
+            let conn = pool.get_timeout(timeout).map_err(|e| e.to_string())?;
+            ddb::ping(&conn).map_err(|e| e.to_string())?;
+            Ok(ddb::migrations::has_pending(&conn).map_err(|e| e.to_string()))
+        }),
+    )
+    .await;
+    let latency_ms = started_at.elapsed().as_millis() as u64;
+
+    let (database, migrations) = match probe {
+        Ok(Ok(pending)) => (
+            Component::up(json!({ "latencyMs": latency_ms })),
+            match pending {
+                Ok(false) => Component::up(json!({ "pending": false })),
+                Ok(true) => Component::down(json!({ "pending": true })),
+                Err(e) => Component::down(json!({ "error": e })),
+            },
+        ),
+        Ok(Err(e)) => (
+            Component::down(json!({ "latencyMs": latency_ms, "error": e.to_string() })),
+            unknown(),
+        ),
+        Err(_) => (
+            Component::down(json!({ "latencyMs": latency_ms, "error": "timed out" })),
+            unknown(),
+        ),
+    };
+
+    let components = Components {
+        database,
+        migrations,
+        pool: pool_component,
+    };
+    let status = if [
+        &components.database,
+        &components.migrations,
+        &components.pool,
+    ]
+    .iter()
+    .all(|v| v.status == Status::Up)
+    {
+        Status::Up
+    } else {
+        Status::Down
+    };
+
+    Report { status, components }
+}
+
+fn check_pool(pool: &ddb::Pool, max_utilisation: f64) -> Component {
+    let state = pool.state();
+    let max_size = pool.max_size();
+    let in_use = state.connections.saturating_sub(state.idle_connections);
+    let utilisation = f64::from(in_use) / f64::from(max_size);
+
+    let details = json!({
+        "connections": state.connections,
+        "idleConnections": state.idle_connections,
+        "maxSize": max_size,
+        "utilisation": utilisation,
+    });
+    if utilisation >= max_utilisation {
+        Component::down(details)
+    } else {
+        Component::up(details)
+    }
+}
+
+fn unknown() -> Component {
+    Component::down(json!({ "error": "database unavailable" }))
+}
@@ -0,0 +1,82 @@
+use crate::ddb::DaoResult;
+use crate::domain;
+use crate::domain::clock::Clock;
+use crate::domain::id::{IdGenerator, UserId};
+use crate::repository::Repositories;
+use serde::Deserialize;
+
+/// Rows per multi-row `INSERT`, well below MySQL's placeholder limit.
+pub const BATCH_SIZE: usize = 500;
+/// Largest import accepted through GraphQL, the admin CLI has no limit.
+pub const MAX_ROWS_PER_REQUEST: usize = 1000;
+
+const MAX_URL_LEN: usize = 255;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRow {
+    pub url: String,
+    #[serde(alias = "is_public")]
+    pub is_public: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct RowResult {
+    pub index: usize,
+    pub result: Result<domain::photo::Photo, String>,
+}
+
+impl ImportRow {
+    fn validate(&self) -> Result<(), String> {
+        let url = self.url.trim();
+        if url.is_empty() {
+            return Err("url must not be empty".to_string());
+        }
+        if url.len() > MAX_URL_LEN {
+            return Err(format!("url must be at most {} bytes", MAX_URL_LEN));
+        }
+        Ok(())
+    }
+}
+
+/// Validates every row, then inserts the valid ones for `user_id` in batches inside one transaction.
+///
+/// Invalid rows are reported and skipped, a database error rolls back the whole import.
+pub fn import_photos(
+    repositories: &Repositories,
+    user_id: UserId,
+    rows: Vec<ImportRow>,
+    ids: &dyn IdGenerator,
+    clock: &dyn Clock,
+) -> DaoResult<Vec<RowResult>> {
+    let results = rows
+        .into_iter()
+        .enumerate()
+        .map(|(index, row)| RowResult {
+            index,
+            result: row.validate().map(|_| {
+                domain::photo::Photo::new(
+                    user_id,
+                    row.url.trim().to_string(),
+                    row.is_public,
+                    ids,
+                    clock,
+                )
+            }),
+        })
+        .collect::<Vec<_>>();
+    let photos = results
+        .iter()
+        .filter_map(|v| v.result.as_ref().ok().cloned())
+        .collect::<Vec<_>>();
+
+    repositories.transaction(|| {
+        repositories.users.get(user_id)?;
+        for chunk in photos.chunks(BATCH_SIZE) {
+            repositories.photos.insert_all(chunk)?;
+        }
+        Ok(())
+    })?;
+
+    Ok(results)
+}
@@ -0,0 +1,18 @@
+#[macro_use]
+extern crate diesel;
+
+pub mod cli;
+pub mod config;
+pub mod cors;
+pub mod ddb;
+pub mod domain;
+pub mod export;
+pub mod graphql;
+pub mod health;
+pub mod import;
+pub mod metrics;
+pub mod rate_limit;
+pub mod repository;
+pub mod seed;
+pub mod server;
+pub mod telemetry;
@@ -1,61 +1,88 @@
-#[macro_use]
-extern crate diesel;
-
 use std::env;
+use std::fmt::Display;
+use std::io;
+use std::process;
+use std::sync::Arc;
 
-use actix_web::{error, web, App, HttpRequest, HttpResponse, HttpServer};
+use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
-use juniper_actix::{graphql_handler, playground_handler};
 
-mod ddb;
-mod domain;
-mod graphql;
+use rust_graphql_diesel_sample::cli::{self, Command};
+use rust_graphql_diesel_sample::config::Config;
+use rust_graphql_diesel_sample::ddb;
+use rust_graphql_diesel_sample::domain::clock::SystemClock;
+use rust_graphql_diesel_sample::domain::id::TimeOrderedIdGenerator;
+use rust_graphql_diesel_sample::server::{self, AppState};
+use rust_graphql_diesel_sample::telemetry::{self, RequestTracing};
+use tracing::info;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    let port = env::var("PORT").unwrap_or("8080".to_string());
-
-    println!("running server on port {}", port);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let command = Command::parse(&args).unwrap_or_else(|| {
+        eprintln!("{}", cli::USAGE);
+        process::exit(2)
+    });
 
-    HttpServer::new(|| {
-        let schema = graphql::new_schema();
+    let config = Config::load().unwrap_or_else(|e| exit_with("failed to start", e));
+    telemetry::init(&config.logging);
+    let pool = ddb::new_pool(&config.database)
+        .unwrap_or_else(|e| exit_with("failed to connect to the database", e));
 
-        App::new()
-            .data(schema)
-            .service(
-                web::resource("/graphql")
-                    .route(web::post().to(graphql_route))
-                    .route(web::get().to(graphql_route)),
-            )
-            .service(web::resource("/playground").route(web::get().to(playground_route)))
-            .service(web::resource("/health_check").route(web::get().to(health_check_route)))
-    })
-    .bind(format!("0.0.0.0:{}", port))
-    .unwrap()
-    .run()
-    .await
+    match command {
+        Command::Serve => serve(config, pool).await,
+        Command::Migrate(v) => {
+            cli::migrate(&pool, v).unwrap_or_else(|e| exit_with("migration failed", e));
+            Ok(())
+        }
+        Command::Seed(v) => {
+            cli::seed(&pool, v).unwrap_or_else(|e| exit_with("seeding failed", e));
+            Ok(())
+        }
+    }
 }
 
-async fn health_check_route() -> actix_web::Result<HttpResponse> {
-    Ok(HttpResponse::Ok().body("ok"))
-}
+async fn serve(config: Config, pool: ddb::Pool) -> io::Result<()> {
+    if config.database.run_migrations_on_startup {
+        let conn = pool
+            .get()
+            .unwrap_or_else(|e| exit_with("failed to connect to the database", e));
+        ddb::migrations::run_pending(&conn, &mut io::sink())
+            .unwrap_or_else(|e| exit_with("failed to run migrations", e));
+        info!("migrations are up to date");
+    }
+
+    let state = web::Data::new(
+        AppState::new(
+            config.clone(),
+            pool,
+            Arc::new(SystemClock),
+            Arc::new(TimeOrderedIdGenerator),
+        )
+        .unwrap_or_else(|e| exit_with("failed to start", e)),
+    );
+
+    state.exporter().spawn_sweeper();
+
+    info!(address = %config.server.bind_address(), "running server");
+
+    let mut server = HttpServer::new(move || {
+        let state = state.clone();
+        App::new()
+            .wrap(state.config.cors.build(&state.config.auth.user_id_header))
+            .wrap(RequestTracing::new(&state.config.auth.user_id_header))
+            .configure(|c| server::configure(state, c))
+    });
+    if config.server.workers > 0 {
+        server = server.workers(config.server.workers);
+    }
 
-async fn playground_route() -> actix_web::Result<HttpResponse> {
-    playground_handler("/graphql", None).await
+    server.bind(config.server.bind_address())?.run().await
 }
 
-async fn graphql_route(
-    req: HttpRequest,
-    payload: web::Payload,
-    schema: web::Data<graphql::Schema>,
-) -> actix_web::Result<HttpResponse> {
-    let authorized_user_id = match req.headers().get("x-user-id") {
-        Some(v) => Some(v.to_str().map_err(|e| error::ErrorBadRequest(e))?.into()),
-        None => None,
-    };
-
-    let context = graphql::Context::new(authorized_user_id);
-    graphql_handler(&schema, &context, req, payload).await
+fn exit_with<E: Display>(message: &str, e: E) -> ! {
+    eprintln!("{}: {}", message, e);
+    process::exit(1)
 }
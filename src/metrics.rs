@@ -0,0 +1,126 @@
+use crate::ddb;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+use std::time::Duration;
+
+const MAX_OPERATION_NAME_LEN: usize = 64;
+
+lazy_static! {
+    static ref GRAPHQL_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "graphql_requests_total",
+        "GraphQL operations executed, by operation name, type and result.",
+        &["operation_name", "operation_type", "status"]
+    )
+    .unwrap();
+    static ref GRAPHQL_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "graphql_request_duration_seconds",
+        "Time spent executing GraphQL operations.",
+        &["operation_name", "operation_type"]
+    )
+    .unwrap();
+    static ref RESOLVER_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "graphql_resolver_errors_total",
+        "Errors returned by resolvers, by error code.",
+        &["code"]
+    )
+    .unwrap();
+    static ref LOADER_LOADS: IntCounterVec = register_int_counter_vec!(
+        "dataloader_loads_total",
+        "Keys requested from a dataloader, whether or not they were cached.",
+        &["loader"]
+    )
+    .unwrap();
+    static ref LOADER_FETCHED_KEYS: IntCounterVec = register_int_counter_vec!(
+        "dataloader_fetched_keys_total",
+        "Keys a dataloader had to fetch from the database, the rest were cache hits.",
+        &["loader"]
+    )
+    .unwrap();
+    static ref LOADER_BATCH_SIZE: HistogramVec = register_histogram_vec!(
+        "dataloader_batch_size",
+        "Keys per dataloader batch.",
+        &["loader"],
+        vec![1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0]
+    )
+    .unwrap();
+    static ref DAO_QUERY_DURATION: HistogramVec = register_histogram_vec!(
+        "dao_query_duration_seconds",
+        "Time spent running DAO queries, by DAO method.",
+        &["method"],
+        vec![0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    )
+    .unwrap();
+    static ref DB_POOL_CONNECTIONS: IntGauge = register_int_gauge!(
+        "db_pool_connections",
+        "Connections currently held by the pool."
+    )
+    .unwrap();
+    static ref DB_POOL_IDLE_CONNECTIONS: IntGauge =
+        register_int_gauge!("db_pool_idle_connections", "Idle connections in the pool.").unwrap();
+    static ref DB_POOL_MAX_CONNECTIONS: IntGauge = register_int_gauge!(
+        "db_pool_max_connections",
+        "Maximum connections the pool will open."
+    )
+    .unwrap();
+}
+
+/// Client supplied operation names become label values, so anything unusual is bucketed as `other`.
+fn operation_label(name: &str) -> &str {
+    let valid = name.len() <= MAX_OPERATION_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        name
+    } else {
+        "other"
+    }
+}
+
+pub fn observe_operation(operation_name: &str, operation_type: &str, ok: bool, elapsed: Duration) {
+    let operation_name = operation_label(operation_name);
+    let status = if ok { "ok" } else { "error" };
+
+    GRAPHQL_REQUESTS
+        .with_label_values(&[operation_name, operation_type, status])
+        .inc();
+    GRAPHQL_REQUEST_DURATION
+        .with_label_values(&[operation_name, operation_type])
+        .observe(elapsed.as_secs_f64());
+}
+
+pub fn observe_resolver_error(code: &str) {
+    RESOLVER_ERRORS.with_label_values(&[code]).inc();
+}
+
+pub fn observe_loader_load(loader: &str) {
+    LOADER_LOADS.with_label_values(&[loader]).inc();
+}
+
+pub fn observe_loader_batch(loader: &str, keys: usize) {
+    LOADER_FETCHED_KEYS
+        .with_label_values(&[loader])
+        .inc_by(keys as u64);
+    LOADER_BATCH_SIZE
+        .with_label_values(&[loader])
+        .observe(keys as f64);
+}
+
+pub fn observe_dao_query(method: &str, elapsed: Duration) {
+    DAO_QUERY_DURATION
+        .with_label_values(&[method])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Renders every registered metric in the Prometheus text format, sampling the pool first.
+pub fn render(pool: &ddb::Pool) -> Result<String, prometheus::Error> {
+    let state = pool.state();
+    DB_POOL_CONNECTIONS.set(i64::from(state.connections));
+    DB_POOL_IDLE_CONNECTIONS.set(i64::from(state.idle_connections));
+    DB_POOL_MAX_CONNECTIONS.set(i64::from(pool.max_size()));
+
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
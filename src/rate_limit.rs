@@ -0,0 +1,321 @@
+use crate::graphql::errors::{request_error_body, FieldErrorCode};
+use crate::telemetry::RequestId;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{web, Error, FromRequest, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::{ready, Future, Ready};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Buckets kept before full ones are dropped, at most once per `PRUNE_INTERVAL`.
+const PRUNE_THRESHOLD: usize = 10_000;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Budget {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Budget {
+    fn refill_per_second(&self) -> f64 {
+        f64::from(self.per_minute) / 60.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OperationKind {
+    Query,
+    Mutation,
+}
+
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationKind::Query => "query",
+            OperationKind::Mutation => "mutation",
+        }
+    }
+}
+
+/// Storage for token buckets, so limits can be shared between instances by swapping the backend.
+pub trait RateLimitStore: Send + Sync {
+    /// Takes one token from each bucket under `keys` when every one of them has a token, otherwise
+    /// takes none and returns how long until they all have one.
+    fn acquire(&self, keys: &[&str], budget: Budget, now: Instant) -> Result<(), Duration>;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, budget: Budget, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * budget.refill_per_second()).min(f64::from(budget.burst));
+        self.updated_at = now;
+    }
+
+    /// How long until the next whole token, `None` when there is one already.
+    fn wait(&self, budget: Budget) -> Option<Duration> {
+        if self.tokens >= 1.0 {
+            return None;
+        }
+
+        let rate = budget.refill_per_second();
+        if rate <= 0.0 {
+            return Some(Duration::from_secs(60));
+        }
+        Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    by_key: HashMap<String, (Bucket, Budget)>,
+    pruned_at: Option<Instant>,
+}
+
+impl Buckets {
+    /// Drops full buckets once there are too many, but not on every call: a store full of active
+    /// clients would otherwise walk every bucket for each request.
+    fn prune(&mut self, now: Instant) {
+        if self.by_key.len() <= PRUNE_THRESHOLD {
+            return;
+        }
+        if let Some(v) = self.pruned_at {
+            if now.saturating_duration_since(v) < PRUNE_INTERVAL {
+                return;
+            }
+        }
+
+        // full buckets carry no state worth keeping
+        self.by_key.retain(|_, (bucket, v)| {
+            bucket.refill(*v, now);
+            bucket.tokens < f64::from(v.burst)
+        });
+        self.pruned_at = Some(now);
+    }
+
+    fn refilled(&mut self, key: &str, budget: Budget, now: Instant) -> &mut Bucket {
+        let (bucket, _) = self.by_key.entry(key.to_string()).or_insert_with(|| {
+            (
+                Bucket {
+                    tokens: f64::from(budget.burst),
+                    updated_at: now,
+                },
+                budget,
+            )
+        });
+        bucket.refill(budget, now);
+        bucket
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryStore {
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimitStore for InMemoryStore {
+    fn acquire(&self, keys: &[&str], budget: Budget, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        buckets.prune(now);
+
+        let wait = keys
+            .iter()
+            .filter_map(|v| buckets.refilled(v, budget, now).wait(budget))
+            .max();
+        if let Some(v) = wait {
+            return Err(v);
+        }
+
+        for key in keys {
+            buckets.refilled(key, budget, now).tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+/// Token bucket limiter charging the client address for every call, and on top of that the
+/// authenticated user.
+pub struct RateLimiter {
+    store: Box<dyn RateLimitStore>,
+    query_budget: Budget,
+    mutation_budget: Budget,
+    trust_forwarded_for: bool,
+}
+
+impl RateLimiter {
+    pub fn new(
+        store: Box<dyn RateLimitStore>,
+        query_budget: Budget,
+        mutation_budget: Budget,
+        trust_forwarded_for: bool,
+    ) -> Self {
+        RateLimiter {
+            store,
+            query_budget,
+            mutation_budget,
+            trust_forwarded_for,
+        }
+    }
+
+    /// `X-Forwarded-For` can be set by anyone, so it is only used when running behind a trusted proxy.
+    pub fn client_ip(&self, req: &HttpRequest) -> String {
+        if self.trust_forwarded_for {
+            if let Some(v) = req.connection_info().realip_remote_addr() {
+                return strip_port(v);
+            }
+        }
+        req.peer_addr()
+            .map(|v| v.ip().to_string())
+            .unwrap_or_default()
+    }
+
+    /// The user id is only a header, so it can't replace the address: a client rotating it would
+    /// get a fresh bucket per request.
+    pub fn check(
+        &self,
+        user_id: Option<&str>,
+        client_ip: &str,
+        kind: OperationKind,
+    ) -> Result<(), Duration> {
+        let budget = match kind {
+            OperationKind::Query => self.query_budget,
+            OperationKind::Mutation => self.mutation_budget,
+        };
+        let ip_key = format!("ip:{}:{}", client_ip, kind.as_str());
+        let user_key = user_id.map(|v| format!("user:{}:{}", v, kind.as_str()));
+
+        // all or nothing, so a throttled user doesn't use up the tokens of the address
+        let mut keys = vec![ip_key.as_str()];
+        keys.extend(user_key.as_deref());
+        self.store.acquire(&keys, budget, Instant::now())
+    }
+}
+
+/// `realip_remote_addr` is the peer address including its port when no forwarding header is set,
+/// which would give every connection its own bucket.
+fn strip_port(addr: &str) -> String {
+    match addr.parse::<SocketAddr>() {
+        Ok(v) => v.ip().to_string(),
+        Err(_) => addr.to_string(),
+    }
+}
+
+/// Works out the operations a request runs before any token is taken, or the response to send
+/// instead when it can't be run at all.
+pub type Classify = fn(&ServiceRequest, web::Bytes) -> Result<Vec<OperationKind>, HttpResponse>;
+
+/// Charges every operation of a request to the limiter and answers `429` with `Retry-After` once a
+/// bucket is empty.
+pub struct RateLimit {
+    limiter: Arc<RateLimiter>,
+    user_id_header: String,
+    classify: Classify,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Arc<RateLimiter>, user_id_header: &str, classify: Classify) -> Self {
+        RateLimit {
+            limiter,
+            user_id_header: user_id_header.to_string(),
+            classify,
+        }
+    }
+}
+
+impl<S> Transform<S> for RateLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(RefCell::new(service)),
+            limiter: Arc::clone(&self.limiter),
+            user_id_header: self.user_id_header.clone(),
+            classify: self.classify,
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<RefCell<S>>,
+    limiter: Arc<RateLimiter>,
+    user_id_header: String,
+    classify: Classify,
+}
+
+impl<S> Service for RateLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error> + 'static,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limiter = Arc::clone(&self.limiter);
+        let classify = self.classify;
+        let user_id = req
+            .headers()
+            .get(self.user_id_header.as_str())
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        Box::pin(async move {
+            let body = {
+                let (http_req, payload) = req.parts_mut();
+                web::Bytes::from_request(http_req, payload).await?
+            };
+            let kinds = match classify(&req, body) {
+                Ok(v) => v,
+                Err(res) => return Ok(req.into_response(res)),
+            };
+
+            let client_ip = limiter.client_ip(req.request());
+            for kind in kinds {
+                if let Err(retry_after) = limiter.check(user_id.as_deref(), &client_ip, kind) {
+                    let retry_after = (retry_after.as_secs_f64().ceil() as u64).max(1);
+                    let res = HttpResponse::TooManyRequests()
+                        .set_header(header::RETRY_AFTER, retry_after.to_string())
+                        .json(request_error_body(
+                            FieldErrorCode::RateLimited,
+                            "rate limit exceeded".to_string(),
+                            &RequestId::of(req.request()),
+                        ));
+                    return Ok(req.into_response(res));
+                }
+            }
+
+            let fut = service.borrow_mut().call(req);
+            fut.await
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use crate::ddb::{self, DaoResult, OrderBy};
+use crate::domain;
+use crate::domain::id::{PhotoId, UserId};
+use crate::metrics;
+use async_trait::async_trait;
+use dataloader::{cached, BatchFn};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub mod memory;
+pub mod sql;
+
+type User = domain::user::User;
+type Photo = domain::photo::Photo;
+type DataExport = domain::data_export::DataExport;
+type AuditEvent = domain::audit_event::AuditEvent;
+
+pub trait UserRepository: Send + Sync {
+    fn get(&self, id: UserId) -> DaoResult<User>;
+    fn get_with_photos(&self, id: UserId) -> DaoResult<(User, Vec<Photo>)>;
+    fn get_all_with_photos(&self) -> DaoResult<Vec<(User, Vec<Photo>)>>;
+    fn find(
+        &self,
+        filter: &ddb::user::Filter,
+        order_by: OrderBy<ddb::user::OrderField>,
+    ) -> DaoResult<Vec<User>>;
+    fn search(&self, query: String) -> DaoResult<Vec<User>>;
+    fn insert(&self, item: &User) -> DaoResult<()>;
+    /// Fails with `DaoError::Conflict` unless the stored row still has `item.version - 1`.
+    fn update(&self, item: &User) -> DaoResult<()>;
+    fn delete(&self, id: UserId) -> DaoResult<bool>;
+    fn batch_get(&self, ids: Vec<UserId>) -> HashMap<UserId, DaoResult<Vec<User>>>;
+}
+
+pub trait PhotoRepository: Send + Sync {
+    fn get(&self, id: PhotoId) -> DaoResult<Photo>;
+    /// Every photo in `ids` that exists, ordered by id.
+    fn get_all(&self, ids: Vec<PhotoId>) -> DaoResult<Vec<Photo>>;
+    fn get_all_by_user(&self, user_id: UserId) -> DaoResult<Vec<Photo>>;
+    fn find(
+        &self,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> DaoResult<Vec<Photo>>;
+    fn find_with_user(
+        &self,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> DaoResult<Vec<(Photo, User)>>;
+    fn insert(&self, item: &Photo) -> DaoResult<()>;
+    fn insert_all(&self, items: &[Photo]) -> DaoResult<usize>;
+    /// Fails with `DaoError::Conflict` unless the stored row still has `item.version - 1`.
+    fn update(&self, item: &Photo) -> DaoResult<()>;
+    fn delete(&self, id: PhotoId) -> DaoResult<bool>;
+    /// Owner of every photo in `ids` that exists, locked until the transaction ends where supported.
+    fn get_owners_for_update(&self, ids: Vec<PhotoId>) -> DaoResult<HashMap<PhotoId, UserId>>;
+    fn update_visibility_all(
+        &self,
+        ids: Vec<PhotoId>,
+        is_public: bool,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize>;
+    fn delete_all(&self, ids: Vec<PhotoId>) -> DaoResult<usize>;
+    fn delete_all_by_user(&self, user_id: UserId) -> DaoResult<usize>;
+    fn reassign(
+        &self,
+        from_user_id: UserId,
+        to_user_id: UserId,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize>;
+    fn batch_get_all_by_user(
+        &self,
+        user_ids: Vec<UserId>,
+    ) -> HashMap<UserId, DaoResult<Vec<Photo>>>;
+}
+
+pub trait DataExportRepository: Send + Sync {
+    fn get(&self, id: String) -> DaoResult<DataExport>;
+    fn get_all_by_user(&self, user_id: UserId) -> DaoResult<Vec<DataExport>>;
+    fn insert(&self, item: &DataExport) -> DaoResult<()>;
+    fn update(&self, item: &DataExport) -> DaoResult<()>;
+}
+
+pub trait AuditEventRepository: Send + Sync {
+    /// Newest first, at most `limit` events and only those after `after`.
+    fn find(
+        &self,
+        filter: &ddb::audit_event::Filter,
+        after: Option<&ddb::audit_event::Cursor>,
+        limit: usize,
+    ) -> DaoResult<Vec<AuditEvent>>;
+    fn insert_all(&self, items: &[AuditEvent]) -> DaoResult<usize>;
+}
+
+/// Transaction control for whatever the repositories store their data in.
+pub trait Transactor: Send + Sync {
+    fn begin(&self) -> DaoResult<()>;
+    fn commit(&self) -> DaoResult<()>;
+    fn rollback(&self) -> DaoResult<()>;
+}
+
+/// The repositories a request works with, all sharing one connection or store.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub photos: Arc<dyn PhotoRepository>,
+    pub data_exports: Arc<dyn DataExportRepository>,
+    pub audit_events: Arc<dyn AuditEventRepository>,
+    transactor: Arc<dyn Transactor>,
+}
+
+impl Repositories {
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        photos: Arc<dyn PhotoRepository>,
+        data_exports: Arc<dyn DataExportRepository>,
+        audit_events: Arc<dyn AuditEventRepository>,
+        transactor: Arc<dyn Transactor>,
+    ) -> Self {
+        Repositories {
+            users,
+            photos,
+            data_exports,
+            audit_events,
+            transactor,
+        }
+    }
+
+    /// Runs `f` in a transaction, committed when it returns `Ok` and rolled back otherwise.
+    pub fn transaction<R, F>(&self, f: F) -> DaoResult<R>
+    where
+        F: FnOnce() -> DaoResult<R>,
+    {
+        self.transactor.begin()?;
+        match f() {
+            Ok(value) => {
+                self.transactor.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.transactor.rollback()?;
+                Err(e)
+            }
+        }
+    }
+}
+
+pub struct UserBatch {
+    repository: Arc<dyn UserRepository>,
+}
+
+#[async_trait]
+impl BatchFn<UserId, DaoResult<Vec<User>>> for UserBatch {
+    async fn load(&mut self, keys: &[UserId]) -> HashMap<UserId, DaoResult<Vec<User>>> {
+        metrics::observe_loader_batch("user_loader", keys.len());
+        self.repository.batch_get(keys.to_vec())
+    }
+}
+
+pub type UserLoader = cached::Loader<UserId, DaoResult<Vec<User>>, UserBatch>;
+
+pub fn new_user_loader(repository: Arc<dyn UserRepository>) -> UserLoader {
+    cached::Loader::new(UserBatch { repository }).with_max_batch_size(100)
+}
+
+pub struct PhotoBatch {
+    repository: Arc<dyn PhotoRepository>,
+}
+
+#[async_trait]
+impl BatchFn<UserId, DaoResult<Vec<Photo>>> for PhotoBatch {
+    async fn load(&mut self, keys: &[UserId]) -> HashMap<UserId, DaoResult<Vec<Photo>>> {
+        metrics::observe_loader_batch("photo_loader", keys.len());
+        self.repository.batch_get_all_by_user(keys.to_vec())
+    }
+}
+
+pub type PhotoLoader = cached::Loader<UserId, DaoResult<Vec<Photo>>, PhotoBatch>;
+
+pub fn new_photo_loader(repository: Arc<dyn PhotoRepository>) -> PhotoLoader {
+    cached::Loader::new(PhotoBatch { repository }).with_max_batch_size(100)
+}
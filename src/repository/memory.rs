@@ -0,0 +1,592 @@
+use crate::ddb::{self, DaoError, DaoResult, OrderBy, SortDirection};
+use crate::domain;
+use crate::domain::id::{AuditEventId, PhotoId, UserId};
+use crate::repository::{
+    AuditEventRepository, DataExportRepository, PhotoRepository, Repositories, Transactor,
+    UserRepository,
+};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type User = domain::user::User;
+type Photo = domain::photo::Photo;
+type DataExport = domain::data_export::DataExport;
+type AuditEvent = domain::audit_event::AuditEvent;
+
+#[derive(Debug, Clone, Default)]
+struct Tables {
+    users: BTreeMap<UserId, User>,
+    photos: BTreeMap<PhotoId, Photo>,
+    data_exports: BTreeMap<String, DataExport>,
+    audit_events: BTreeMap<AuditEventId, AuditEvent>,
+}
+
+/// Tables shared by the in-memory repositories, with one snapshot per open transaction.
+#[derive(Debug, Default)]
+struct Store {
+    tables: Tables,
+    snapshots: Vec<Tables>,
+}
+
+type SharedStore = Arc<Mutex<Store>>;
+
+fn lock(store: &SharedStore) -> MutexGuard<Store> {
+    store.lock().unwrap()
+}
+
+impl Repositories {
+    /// Repositories keeping everything in process memory, for tests and local experiments.
+    ///
+    /// Constraints the MySQL schema enforces are checked as well: duplicate ids, photos of
+    /// unknown users, users that still own photos, and version conflicts on update.
+    pub fn in_memory() -> Self {
+        let store: SharedStore = Arc::new(Mutex::new(Store::default()));
+        Repositories::new(
+            Arc::new(MemoryUserRepository {
+                store: Arc::clone(&store),
+            }),
+            Arc::new(MemoryPhotoRepository {
+                store: Arc::clone(&store),
+            }),
+            Arc::new(MemoryDataExportRepository {
+                store: Arc::clone(&store),
+            }),
+            Arc::new(MemoryAuditEventRepository {
+                store: Arc::clone(&store),
+            }),
+            Arc::new(MemoryTransactor { store }),
+        )
+    }
+}
+
+fn direction(ordering: Ordering, direction: SortDirection) -> Ordering {
+    match direction {
+        SortDirection::Asc => ordering,
+        SortDirection::Desc => ordering.reverse(),
+    }
+}
+
+fn newest_first<T, F>(items: &mut [T], created_at: F)
+where
+    F: Fn(&T) -> chrono::NaiveDateTime,
+{
+    items.sort_by(|a, b| created_at(b).cmp(&created_at(a)));
+}
+
+/// Name matching of the SQL backends, which all compare case-insensitively.
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+fn duplicate(table: &str, id: &dyn std::fmt::Display) -> DaoError {
+    DaoError::Internal(format!(
+        "duplicate entry '{}' for key '{}.PRIMARY'",
+        id, table
+    ))
+}
+
+fn foreign_key(table: &str, column: &str) -> DaoError {
+    DaoError::Internal(format!(
+        "foreign key constraint fails on '{}.{}'",
+        table, column
+    ))
+}
+
+fn photos_of(tables: &Tables, user_id: &UserId) -> Vec<Photo> {
+    let mut photos = tables
+        .photos
+        .values()
+        .filter(|v| &v.user_id == user_id)
+        .cloned()
+        .collect::<Vec<_>>();
+    newest_first(&mut photos, |v| v.created_at);
+    photos
+}
+
+pub struct MemoryUserRepository {
+    store: SharedStore,
+}
+
+impl UserRepository for MemoryUserRepository {
+    fn get(&self, id: UserId) -> DaoResult<User> {
+        lock(&self.store)
+            .tables
+            .users
+            .get(&id)
+            .cloned()
+            .ok_or(DaoError::NotFound)
+    }
+
+    fn get_with_photos(&self, id: UserId) -> DaoResult<(User, Vec<Photo>)> {
+        let store = lock(&self.store);
+        let user = store
+            .tables
+            .users
+            .get(&id)
+            .cloned()
+            .ok_or(DaoError::NotFound)?;
+        Ok((user, photos_of(&store.tables, &id)))
+    }
+
+    fn get_all_with_photos(&self) -> DaoResult<Vec<(User, Vec<Photo>)>> {
+        let store = lock(&self.store);
+        let mut users = store.tables.users.values().cloned().collect::<Vec<_>>();
+        newest_first(&mut users, |v| v.created_at);
+        Ok(users
+            .into_iter()
+            .map(|v| {
+                let photos = photos_of(&store.tables, &v.id);
+                (v, photos)
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn find(
+        &self,
+        filter: &ddb::user::Filter,
+        order_by: OrderBy<ddb::user::OrderField>,
+    ) -> DaoResult<Vec<User>> {
+        let store = lock(&self.store);
+        let mut users = store
+            .tables
+            .users
+            .values()
+            .filter(|v| filter.exclude_id.as_ref().map_or(true, |id| &v.id != id))
+            .filter(|v| {
+                filter
+                    .name_contains
+                    .as_ref()
+                    .map_or(true, |name| contains_ignore_case(&v.name, name))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        users.sort_by(|a, b| {
+            let ordering = match order_by.field {
+                ddb::user::OrderField::CreatedAt => a.created_at.cmp(&b.created_at),
+                ddb::user::OrderField::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            };
+            direction(ordering, order_by.direction).then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(users)
+    }
+
+    fn search(&self, query: String) -> DaoResult<Vec<User>> {
+        let store = lock(&self.store);
+        let mut users = store
+            .tables
+            .users
+            .values()
+            .filter(|v| contains_ignore_case(&v.name, &query))
+            .cloned()
+            .collect::<Vec<_>>();
+        newest_first(&mut users, |v| v.created_at);
+        Ok(users)
+    }
+
+    fn insert(&self, item: &User) -> DaoResult<()> {
+        let mut store = lock(&self.store);
+        if store.tables.users.contains_key(&item.id) {
+            return Err(duplicate("users", &item.id));
+        }
+        store.tables.users.insert(item.id, item.clone());
+        Ok(())
+    }
+
+    fn update(&self, item: &User) -> DaoResult<()> {
+        let mut store = lock(&self.store);
+        match store.tables.users.get_mut(&item.id) {
+            Some(v) if v.version == item.version - 1 => {
+                v.name = item.name.clone();
+                v.version = item.version;
+                v.updated_at = item.updated_at;
+                Ok(())
+            }
+            _ => Err(DaoError::Conflict),
+        }
+    }
+
+    fn delete(&self, id: UserId) -> DaoResult<bool> {
+        let mut store = lock(&self.store);
+        if store.tables.photos.values().any(|v| v.user_id == id) {
+            return Err(foreign_key("photos", "user_id"));
+        }
+        store.tables.users.remove(&id);
+        // data_exports.user_id is declared ON DELETE CASCADE
+        store.tables.data_exports.retain(|_, v| v.user_id != id);
+        Ok(true)
+    }
+
+    fn batch_get(&self, ids: Vec<UserId>) -> HashMap<UserId, DaoResult<Vec<User>>> {
+        let store = lock(&self.store);
+        ids.into_iter()
+            .map(|id| {
+                let users = store.tables.users.get(&id).cloned().into_iter().collect();
+                (id, Ok(users))
+            })
+            .collect::<HashMap<_, _>>()
+    }
+}
+
+pub struct MemoryPhotoRepository {
+    store: SharedStore,
+}
+
+impl MemoryPhotoRepository {
+    fn filtered(
+        tables: &Tables,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> Vec<Photo> {
+        let mut photos = tables
+            .photos
+            .values()
+            .filter(|v| {
+                filter
+                    .is_public
+                    .map_or(true, |is_public| v.is_public == is_public)
+            })
+            .filter(|v| filter.user_id.as_ref().map_or(true, |id| &v.user_id == id))
+            .filter(|v| filter.created_after.map_or(true, |t| v.created_at > t))
+            .filter(|v| filter.created_before.map_or(true, |t| v.created_at < t))
+            .cloned()
+            .collect::<Vec<_>>();
+        photos.sort_by(|a, b| {
+            let ordering = match order_by.field {
+                ddb::photo::OrderField::CreatedAt => a.created_at.cmp(&b.created_at),
+                ddb::photo::OrderField::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            };
+            direction(ordering, order_by.direction).then_with(|| a.id.cmp(&b.id))
+        });
+        photos
+    }
+}
+
+impl PhotoRepository for MemoryPhotoRepository {
+    fn get(&self, id: PhotoId) -> DaoResult<Photo> {
+        lock(&self.store)
+            .tables
+            .photos
+            .get(&id)
+            .cloned()
+            .ok_or(DaoError::NotFound)
+    }
+
+    fn get_all(&self, ids: Vec<PhotoId>) -> DaoResult<Vec<Photo>> {
+        let store = lock(&self.store);
+        Ok(store
+            .tables
+            .photos
+            .values()
+            .filter(|v| ids.contains(&v.id))
+            .cloned()
+            .collect::<Vec<_>>())
+    }
+
+    fn get_all_by_user(&self, user_id: UserId) -> DaoResult<Vec<Photo>> {
+        Ok(photos_of(&lock(&self.store).tables, &user_id))
+    }
+
+    fn find(
+        &self,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> DaoResult<Vec<Photo>> {
+        Ok(Self::filtered(&lock(&self.store).tables, filter, order_by))
+    }
+
+    fn find_with_user(
+        &self,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> DaoResult<Vec<(Photo, User)>> {
+        let store = lock(&self.store);
+        Ok(Self::filtered(&store.tables, filter, order_by)
+            .into_iter()
+            .filter_map(|v| {
+                let user = store.tables.users.get(&v.user_id)?.clone();
+                Some((v, user))
+            })
+            .collect::<Vec<_>>())
+    }
+
+    fn insert(&self, item: &Photo) -> DaoResult<()> {
+        self.insert_all(std::slice::from_ref(item)).map(|_| ())
+    }
+
+    fn insert_all(&self, items: &[Photo]) -> DaoResult<usize> {
+        let mut store = lock(&self.store);
+        // checked up front so a failing row leaves the table untouched, like one INSERT statement
+        for item in items {
+            if store.tables.photos.contains_key(&item.id) {
+                return Err(duplicate("photos", &item.id));
+            }
+            if !store.tables.users.contains_key(&item.user_id) {
+                return Err(foreign_key("photos", "user_id"));
+            }
+        }
+        for item in items {
+            store.tables.photos.insert(item.id, item.clone());
+        }
+        Ok(items.len())
+    }
+
+    fn update(&self, item: &Photo) -> DaoResult<()> {
+        let mut store = lock(&self.store);
+        match store.tables.photos.get_mut(&item.id) {
+            Some(v) if v.version == item.version - 1 => {
+                v.is_public = item.is_public;
+                v.version = item.version;
+                v.updated_at = item.updated_at;
+                Ok(())
+            }
+            _ => Err(DaoError::Conflict),
+        }
+    }
+
+    fn delete(&self, id: PhotoId) -> DaoResult<bool> {
+        lock(&self.store).tables.photos.remove(&id);
+        Ok(true)
+    }
+
+    fn get_owners_for_update(&self, ids: Vec<PhotoId>) -> DaoResult<HashMap<PhotoId, UserId>> {
+        let store = lock(&self.store);
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| {
+                let user_id = store.tables.photos.get(&id)?.user_id;
+                Some((id, user_id))
+            })
+            .collect::<HashMap<_, _>>())
+    }
+
+    fn update_visibility_all(
+        &self,
+        ids: Vec<PhotoId>,
+        is_public: bool,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize> {
+        let mut store = lock(&self.store);
+        let mut updated = 0;
+        for v in store
+            .tables
+            .photos
+            .values_mut()
+            .filter(|v| ids.contains(&v.id))
+        {
+            v.is_public = is_public;
+            v.version += 1;
+            v.updated_at = now;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    fn delete_all(&self, ids: Vec<PhotoId>) -> DaoResult<usize> {
+        let mut store = lock(&self.store);
+        let before = store.tables.photos.len();
+        store.tables.photos.retain(|id, _| !ids.contains(id));
+        Ok(before - store.tables.photos.len())
+    }
+
+    fn delete_all_by_user(&self, user_id: UserId) -> DaoResult<usize> {
+        let mut store = lock(&self.store);
+        let before = store.tables.photos.len();
+        store.tables.photos.retain(|_, v| v.user_id != user_id);
+        Ok(before - store.tables.photos.len())
+    }
+
+    fn reassign(
+        &self,
+        from_user_id: UserId,
+        to_user_id: UserId,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize> {
+        let mut store = lock(&self.store);
+        let moving = store
+            .tables
+            .photos
+            .values()
+            .any(|v| v.user_id == from_user_id);
+        if moving && !store.tables.users.contains_key(&to_user_id) {
+            return Err(foreign_key("photos", "user_id"));
+        }
+        let mut updated = 0;
+        for v in store
+            .tables
+            .photos
+            .values_mut()
+            .filter(|v| v.user_id == from_user_id)
+        {
+            v.user_id = to_user_id;
+            v.version += 1;
+            v.updated_at = now;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    fn batch_get_all_by_user(
+        &self,
+        user_ids: Vec<UserId>,
+    ) -> HashMap<UserId, DaoResult<Vec<Photo>>> {
+        let store = lock(&self.store);
+        user_ids
+            .into_iter()
+            .map(|id| {
+                let photos = photos_of(&store.tables, &id);
+                (id, Ok(photos))
+            })
+            .collect::<HashMap<_, _>>()
+    }
+}
+
+pub struct MemoryDataExportRepository {
+    store: SharedStore,
+}
+
+impl DataExportRepository for MemoryDataExportRepository {
+    fn get(&self, id: String) -> DaoResult<DataExport> {
+        lock(&self.store)
+            .tables
+            .data_exports
+            .get(&id)
+            .cloned()
+            .ok_or(DaoError::NotFound)
+    }
+
+    fn get_all_by_user(&self, user_id: UserId) -> DaoResult<Vec<DataExport>> {
+        let store = lock(&self.store);
+        let mut exports = store
+            .tables
+            .data_exports
+            .values()
+            .filter(|v| v.user_id == user_id)
+            .cloned()
+            .collect::<Vec<_>>();
+        newest_first(&mut exports, |v| v.created_at);
+        Ok(exports)
+    }
+
+    fn insert(&self, item: &DataExport) -> DaoResult<()> {
+        let mut store = lock(&self.store);
+        if store.tables.data_exports.contains_key(&item.id) {
+            return Err(duplicate("data_exports", &item.id));
+        }
+        if !store.tables.users.contains_key(&item.user_id) {
+            return Err(foreign_key("data_exports", "user_id"));
+        }
+        store
+            .tables
+            .data_exports
+            .insert(item.id.clone(), item.clone());
+        Ok(())
+    }
+
+    fn update(&self, item: &DataExport) -> DaoResult<()> {
+        if let Some(v) = lock(&self.store).tables.data_exports.get_mut(&item.id) {
+            v.status = item.status;
+            v.file_name = item.file_name.clone();
+            v.error = item.error.clone();
+            v.expires_at = item.expires_at;
+            v.updated_at = item.updated_at;
+        }
+        Ok(())
+    }
+}
+
+pub struct MemoryAuditEventRepository {
+    store: SharedStore,
+}
+
+impl AuditEventRepository for MemoryAuditEventRepository {
+    fn find(
+        &self,
+        filter: &ddb::audit_event::Filter,
+        after: Option<&ddb::audit_event::Cursor>,
+        limit: usize,
+    ) -> DaoResult<Vec<AuditEvent>> {
+        let store = lock(&self.store);
+        let mut events = store
+            .tables
+            .audit_events
+            .values()
+            .filter(|v| filter.actor_id.map_or(true, |id| v.actor_id == id))
+            .filter(|v| filter.action.as_ref().map_or(true, |a| &v.action == a))
+            .filter(|v| {
+                filter
+                    .entity_type
+                    .as_ref()
+                    .map_or(true, |t| &v.entity_type == t)
+            })
+            .filter(|v| {
+                filter
+                    .entity_id
+                    .as_ref()
+                    .map_or(true, |id| &v.entity_id == id)
+            })
+            .filter(|v| {
+                filter
+                    .request_id
+                    .as_ref()
+                    .map_or(true, |id| &v.request_id == id)
+            })
+            .filter(|v| filter.created_after.map_or(true, |t| v.created_at > t))
+            .filter(|v| filter.created_before.map_or(true, |t| v.created_at < t))
+            .filter(|v| after.map_or(true, |c| (v.created_at, &v.id) < (c.created_at, &c.id)))
+            .cloned()
+            .collect::<Vec<_>>();
+        events.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.id.cmp(&a.id))
+        });
+        events.truncate(limit);
+        Ok(events)
+    }
+
+    fn insert_all(&self, items: &[AuditEvent]) -> DaoResult<usize> {
+        let mut store = lock(&self.store);
+        for item in items {
+            if store.tables.audit_events.contains_key(&item.id) {
+                return Err(duplicate("audit_events", &item.id));
+            }
+        }
+        for item in items {
+            store.tables.audit_events.insert(item.id, item.clone());
+        }
+        Ok(items.len())
+    }
+}
+
+/// Transactions are snapshots of every table, restored on rollback.
+pub struct MemoryTransactor {
+    store: SharedStore,
+}
+
+impl Transactor for MemoryTransactor {
+    fn begin(&self) -> DaoResult<()> {
+        let mut store = lock(&self.store);
+        let snapshot = store.tables.clone();
+        store.snapshots.push(snapshot);
+        Ok(())
+    }
+
+    fn commit(&self) -> DaoResult<()> {
+        lock(&self.store)
+            .snapshots
+            .pop()
+            .map(|_| ())
+            .ok_or_else(|| DaoError::Internal("no transaction in progress".to_string()))
+    }
+
+    fn rollback(&self) -> DaoResult<()> {
+        let mut store = lock(&self.store);
+        let snapshot = store
+            .snapshots
+            .pop()
+            .ok_or_else(|| DaoError::Internal("no transaction in progress".to_string()))?;
+        store.tables = snapshot;
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use crate::ddb::{self, Dao, DaoResult, DbConnection, OrderBy};
+use crate::domain;
+use crate::domain::id::{PhotoId, UserId};
+use crate::repository::{
+    AuditEventRepository, DataExportRepository, PhotoRepository, Repositories, Transactor,
+    UserRepository,
+};
+use diesel::connection::{Connection, TransactionManager};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+type User = domain::user::User;
+type Photo = domain::photo::Photo;
+type DataExport = domain::data_export::DataExport;
+type AuditEvent = domain::audit_event::AuditEvent;
+type SharedConnection = Arc<Mutex<ddb::Connection>>;
+
+impl Repositories {
+    /// Repositories backed by `connection` on the database backend the crate was built for,
+    /// every call locks the connection for its duration.
+    pub fn sql(connection: ddb::Connection) -> Self {
+        let conn = Arc::new(Mutex::new(connection));
+        Repositories::new(
+            Arc::new(SqlUserRepository {
+                conn: Arc::clone(&conn),
+                dao: Dao::new(),
+            }),
+            Arc::new(SqlPhotoRepository {
+                conn: Arc::clone(&conn),
+                dao: Dao::new(),
+            }),
+            Arc::new(SqlDataExportRepository {
+                conn: Arc::clone(&conn),
+                dao: Dao::new(),
+            }),
+            Arc::new(SqlAuditEventRepository {
+                conn: Arc::clone(&conn),
+                dao: Dao::new(),
+            }),
+            Arc::new(SqlTransactor { conn }),
+        )
+    }
+}
+
+pub struct SqlUserRepository {
+    conn: SharedConnection,
+    dao: Dao<User>,
+}
+
+impl UserRepository for SqlUserRepository {
+    fn get(&self, id: UserId) -> DaoResult<User> {
+        self.dao.get(&self.conn.lock().unwrap(), id)
+    }
+
+    fn get_with_photos(&self, id: UserId) -> DaoResult<(User, Vec<Photo>)> {
+        self.dao.get_with_photos(&self.conn.lock().unwrap(), id)
+    }
+
+    fn get_all_with_photos(&self) -> DaoResult<Vec<(User, Vec<Photo>)>> {
+        self.dao.get_all_with_photos(&self.conn.lock().unwrap())
+    }
+
+    fn find(
+        &self,
+        filter: &ddb::user::Filter,
+        order_by: OrderBy<ddb::user::OrderField>,
+    ) -> DaoResult<Vec<User>> {
+        self.dao.find(&self.conn.lock().unwrap(), filter, order_by)
+    }
+
+    fn search(&self, query: String) -> DaoResult<Vec<User>> {
+        self.dao.search(&self.conn.lock().unwrap(), query)
+    }
+
+    fn insert(&self, item: &User) -> DaoResult<()> {
+        self.dao.insert(&self.conn.lock().unwrap(), item)
+    }
+
+    fn update(&self, item: &User) -> DaoResult<()> {
+        self.dao.update(&self.conn.lock().unwrap(), item)
+    }
+
+    fn delete(&self, id: UserId) -> DaoResult<bool> {
+        self.dao.delete(&self.conn.lock().unwrap(), id)
+    }
+
+    fn batch_get(&self, ids: Vec<UserId>) -> HashMap<UserId, DaoResult<Vec<User>>> {
+        let mut hashmap = HashMap::new();
+        self.dao
+            .batch_get(&self.conn.lock().unwrap(), &mut hashmap, ids);
+        hashmap
+    }
+}
+
+pub struct SqlPhotoRepository {
+    conn: SharedConnection,
+    dao: Dao<Photo>,
+}
+
+impl PhotoRepository for SqlPhotoRepository {
+    fn get(&self, id: PhotoId) -> DaoResult<Photo> {
+        self.dao.get(&self.conn.lock().unwrap(), id)
+    }
+
+    fn get_all(&self, ids: Vec<PhotoId>) -> DaoResult<Vec<Photo>> {
+        self.dao.get_all(&self.conn.lock().unwrap(), ids)
+    }
+
+    fn get_all_by_user(&self, user_id: UserId) -> DaoResult<Vec<Photo>> {
+        self.dao
+            .get_all_by_user(&self.conn.lock().unwrap(), user_id)
+    }
+
+    fn find(
+        &self,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> DaoResult<Vec<Photo>> {
+        self.dao.find(&self.conn.lock().unwrap(), filter, order_by)
+    }
+
+    fn find_with_user(
+        &self,
+        filter: &ddb::photo::Filter,
+        order_by: OrderBy<ddb::photo::OrderField>,
+    ) -> DaoResult<Vec<(Photo, User)>> {
+        self.dao
+            .find_with_user(&self.conn.lock().unwrap(), filter, order_by)
+    }
+
+    fn insert(&self, item: &Photo) -> DaoResult<()> {
+        self.dao.insert(&self.conn.lock().unwrap(), item)
+    }
+
+    fn insert_all(&self, items: &[Photo]) -> DaoResult<usize> {
+        self.dao.insert_all(&self.conn.lock().unwrap(), items)
+    }
+
+    fn update(&self, item: &Photo) -> DaoResult<()> {
+        self.dao.update(&self.conn.lock().unwrap(), item)
+    }
+
+    fn delete(&self, id: PhotoId) -> DaoResult<bool> {
+        self.dao.delete(&self.conn.lock().unwrap(), id)
+    }
+
+    fn get_owners_for_update(&self, ids: Vec<PhotoId>) -> DaoResult<HashMap<PhotoId, UserId>> {
+        self.dao
+            .get_owners_for_update(&self.conn.lock().unwrap(), ids)
+    }
+
+    fn update_visibility_all(
+        &self,
+        ids: Vec<PhotoId>,
+        is_public: bool,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize> {
+        self.dao
+            .update_visibility_all(&self.conn.lock().unwrap(), ids, is_public, now)
+    }
+
+    fn delete_all(&self, ids: Vec<PhotoId>) -> DaoResult<usize> {
+        self.dao.delete_all(&self.conn.lock().unwrap(), ids)
+    }
+
+    fn delete_all_by_user(&self, user_id: UserId) -> DaoResult<usize> {
+        self.dao
+            .delete_all_by_user(&self.conn.lock().unwrap(), user_id)
+    }
+
+    fn reassign(
+        &self,
+        from_user_id: UserId,
+        to_user_id: UserId,
+        now: chrono::NaiveDateTime,
+    ) -> DaoResult<usize> {
+        self.dao
+            .reassign(&self.conn.lock().unwrap(), from_user_id, to_user_id, now)
+    }
+
+    fn batch_get_all_by_user(
+        &self,
+        user_ids: Vec<UserId>,
+    ) -> HashMap<UserId, DaoResult<Vec<Photo>>> {
+        let mut hashmap = HashMap::new();
+        self.dao
+            .batch_get_all_by_user(&self.conn.lock().unwrap(), &mut hashmap, user_ids);
+        hashmap
+    }
+}
+
+pub struct SqlDataExportRepository {
+    conn: SharedConnection,
+    dao: Dao<DataExport>,
+}
+
+impl DataExportRepository for SqlDataExportRepository {
+    fn get(&self, id: String) -> DaoResult<DataExport> {
+        self.dao.get(&self.conn.lock().unwrap(), id)
+    }
+
+    fn get_all_by_user(&self, user_id: UserId) -> DaoResult<Vec<DataExport>> {
+        self.dao
+            .get_all_by_user(&self.conn.lock().unwrap(), user_id)
+    }
+
+    fn insert(&self, item: &DataExport) -> DaoResult<()> {
+        self.dao.insert(&self.conn.lock().unwrap(), item)
+    }
+
+    fn update(&self, item: &DataExport) -> DaoResult<()> {
+        self.dao.update(&self.conn.lock().unwrap(), item)
+    }
+}
+
+pub struct SqlAuditEventRepository {
+    conn: SharedConnection,
+    dao: Dao<AuditEvent>,
+}
+
+impl AuditEventRepository for SqlAuditEventRepository {
+    fn find(
+        &self,
+        filter: &ddb::audit_event::Filter,
+        after: Option<&ddb::audit_event::Cursor>,
+        limit: usize,
+    ) -> DaoResult<Vec<AuditEvent>> {
+        self.dao
+            .find(&self.conn.lock().unwrap(), filter, after, limit)
+    }
+
+    fn insert_all(&self, items: &[AuditEvent]) -> DaoResult<usize> {
+        self.dao.insert_all(&self.conn.lock().unwrap(), items)
+    }
+}
+
+pub struct SqlTransactor {
+    conn: SharedConnection,
+}
+
+impl Transactor for SqlTransactor {
+    fn begin(&self) -> DaoResult<()> {
+        let conn: &DbConnection = &self.conn.lock().unwrap();
+        conn.transaction_manager().begin_transaction(conn)?;
+        Ok(())
+    }
+
+    fn commit(&self) -> DaoResult<()> {
+        let conn: &DbConnection = &self.conn.lock().unwrap();
+        conn.transaction_manager().commit_transaction(conn)?;
+        Ok(())
+    }
+
+    fn rollback(&self) -> DaoResult<()> {
+        let conn: &DbConnection = &self.conn.lock().unwrap();
+        conn.transaction_manager().rollback_transaction(conn)?;
+        Ok(())
+    }
+}
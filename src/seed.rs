@@ -0,0 +1,186 @@
+use crate::ddb::{Dao, DaoResult, DbConnection, Tx};
+use crate::domain::clock::{Clock, FixedClock};
+use crate::domain::id::{self, IdGenerator, UserId};
+use crate::domain::photo::Photo;
+use crate::domain::user::User;
+use chrono::{DateTime, Duration, Utc};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+const FIRST_NAMES: &[&str] = &[
+    "Aiko", "Ben", "Chloe", "Daisuke", "Emma", "Felix", "Grace", "Haruto", "Isla", "Jun", "Kai",
+    "Lena", "Mia", "Noah", "Olivia", "Ren", "Sara", "Taro", "Yui", "Zoe",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Abe", "Brown", "Clark", "Davis", "Endo", "Fujita", "Garcia", "Hayashi", "Ito", "Jones",
+    "Kato", "Lopez", "Mori", "Nakamura", "Ono", "Smith", "Suzuki", "Tanaka", "Wilson", "Yamada",
+];
+
+/// How far back seeded users were created.
+const HISTORY_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeedOptions {
+    pub seed: u64,
+    pub users: usize,
+    pub photos_per_user: usize,
+    /// Share of photos that are public, between 0 and 1.
+    pub public_ratio: f64,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        SeedOptions {
+            seed: 42,
+            users: 20,
+            photos_per_user: 5,
+            public_ratio: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Seeded {
+    pub users: usize,
+    pub photos: usize,
+}
+
+/// Inserts users with photos in one transaction, with timestamps spread over the year before
+/// `now`. The same seed always yields the same names, urls and visibility, and together with the
+/// same `now` the same ids.
+pub fn seed(conn: &DbConnection, options: SeedOptions, now: DateTime<Utc>) -> DaoResult<Seeded> {
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    let clock = FixedClock::new(now);
+    let ids = SeededIdGenerator::new(options.seed, &clock);
+
+    Tx::run(conn, || {
+        let mut seeded = Seeded {
+            users: 0,
+            photos: 0,
+        };
+        for _ in 0..options.users {
+            let name = format!(
+                "{} {}",
+                FIRST_NAMES[rng.gen_range(0..FIRST_NAMES.len())],
+                LAST_NAMES[rng.gen_range(0..LAST_NAMES.len())]
+            );
+            let created_at = now - Duration::seconds(rng.gen_range(0..HISTORY_DAYS * 86400));
+            clock.set(created_at);
+            let user = UserFactory::new(&name).create(conn, &ids, &clock)?;
+            seeded.users += 1;
+
+            let age = (now - created_at).num_seconds().max(1);
+            for n in 0..options.photos_per_user {
+                let mut photo = PhotoFactory::new(&user).url(&format!(
+                    "https://picsum.photos/seed/{}-{}/640/480",
+                    user.id, n
+                ));
+                if !rng.gen_bool(options.public_ratio) {
+                    photo = photo.private();
+                }
+                clock.set(created_at + Duration::seconds(rng.gen_range(0..age)));
+                photo.create(conn, &ids, &clock)?;
+                seeded.photos += 1;
+            }
+        }
+        Ok(seeded)
+    })
+}
+
+/// Time-ordered ids stamped with `clock` and filled from a seeded generator instead of the OS.
+struct SeededIdGenerator<'a> {
+    rng: Mutex<ChaCha8Rng>,
+    clock: &'a dyn Clock,
+}
+
+impl<'a> SeededIdGenerator<'a> {
+    fn new(seed: u64, clock: &'a dyn Clock) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        // a stream of its own, so the ids don't repeat the numbers behind names and timestamps
+        rng.set_stream(1);
+        SeededIdGenerator {
+            rng: Mutex::new(rng),
+            clock,
+        }
+    }
+}
+
+impl IdGenerator for SeededIdGenerator<'_> {
+    fn generate(&self) -> Uuid {
+        let millis = self.clock.now().timestamp_millis() as u64;
+        id::time_ordered(millis, self.rng.lock().unwrap().gen())
+    }
+}
+
+/// Builds a `User` and inserts it with `Dao::insert`.
+pub struct UserFactory {
+    name: String,
+}
+
+impl UserFactory {
+    pub fn new(name: &str) -> Self {
+        UserFactory {
+            name: name.to_string(),
+        }
+    }
+
+    pub fn build(self, ids: &dyn IdGenerator, clock: &dyn Clock) -> User {
+        User::new(self.name, ids, clock)
+    }
+
+    pub fn create(
+        self,
+        conn: &DbConnection,
+        ids: &dyn IdGenerator,
+        clock: &dyn Clock,
+    ) -> DaoResult<User> {
+        let user = self.build(ids, clock);
+        Dao::<User>::new().insert(conn, &user)?;
+        Ok(user)
+    }
+}
+
+/// Builds a public `Photo` of a user and inserts it with `Dao::insert`.
+pub struct PhotoFactory {
+    user_id: UserId,
+    url: String,
+    is_public: bool,
+}
+
+impl PhotoFactory {
+    pub fn new(user: &User) -> Self {
+        PhotoFactory {
+            user_id: user.id,
+            url: "https://example.com/photo.jpg".to_string(),
+            is_public: true,
+        }
+    }
+
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    pub fn private(mut self) -> Self {
+        self.is_public = false;
+        self
+    }
+
+    pub fn build(self, ids: &dyn IdGenerator, clock: &dyn Clock) -> Photo {
+        Photo::new(self.user_id, self.url, self.is_public, ids, clock)
+    }
+
+    pub fn create(
+        self,
+        conn: &DbConnection,
+        ids: &dyn IdGenerator,
+        clock: &dyn Clock,
+    ) -> DaoResult<Photo> {
+        let photo = self.build(ids, clock);
+        Dao::<Photo>::new().insert(conn, &photo)?;
+        Ok(photo)
+    }
+}
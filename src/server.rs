@@ -0,0 +1,363 @@
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use actix_web::dev::ServiceRequest;
+use actix_web::error::BlockingError;
+use actix_web::http::{header, Method};
+use actix_web::{error, web, HttpRequest, HttpResponse};
+use juniper_actix::playground_handler;
+use tracing::{info, info_span, Instrument};
+
+use crate::config::Config;
+use crate::ddb::{self, Dao, DaoError};
+use crate::domain::clock::Clock;
+use crate::domain::data_export::DataExport;
+use crate::domain::id::{IdGenerator, UserId};
+use crate::export::{DataExporter, DOWNLOAD_PATH};
+use crate::graphql::errors::{request_error_body, with_request_id, FieldErrorCode};
+use crate::graphql::limits::QueryLimits;
+use crate::graphql::persisted::{PersistedQueries, PersistedQueryError};
+use crate::graphql::request::{BatchPayload, GetPayload, Payload};
+use crate::rate_limit::{InMemoryStore, OperationKind, RateLimit, RateLimiter};
+use crate::repository::Repositories;
+use crate::telemetry::RequestId;
+use crate::{graphql, health, metrics};
+
+/// Everything the routes share, built once per process and cloned into every worker.
+pub struct AppState {
+    pub config: Config,
+    pool: ddb::Pool,
+    limits: QueryLimits,
+    persisted_queries: PersistedQueries,
+    rate_limiter: Arc<RateLimiter>,
+    exporter: Arc<DataExporter>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
+    /// Shared by every request instead of one pooled connection each, see `with_repositories`.
+    repositories: Option<Repositories>,
+}
+
+impl AppState {
+    pub fn new(
+        config: Config,
+        pool: ddb::Pool,
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+    ) -> Result<Self, String> {
+        let limits_config = &config.limits;
+        let mut persisted_queries =
+            PersistedQueries::new(limits_config.persisted_queries.cache_size);
+        if let Some(dir) = &limits_config.persisted_queries.allowlist_dir {
+            persisted_queries = persisted_queries
+                .with_allowlist(Path::new(dir))
+                .map_err(|e| format!("failed to load the persisted query allowlist: {}", e))?;
+        }
+
+        Ok(AppState {
+            limits: QueryLimits::new(
+                limits_config.max_depth,
+                limits_config.max_complexity,
+                limits_config.max_aliases,
+            ),
+            persisted_queries,
+            rate_limiter: Arc::new(RateLimiter::new(
+                Box::new(InMemoryStore::default()),
+                limits_config.rate_limit.query,
+                limits_config.rate_limit.mutation,
+                limits_config.rate_limit.trust_forwarded_for,
+            )),
+            exporter: Arc::new(DataExporter::new(
+                pool.clone(),
+                &config.exports,
+                Arc::clone(&clock),
+            )),
+            pool,
+            config,
+            clock,
+            ids,
+            repositories: None,
+        })
+    }
+
+    /// Serves GraphQL from `repositories`, e.g. `Repositories::in_memory` to test resolvers without
+    /// a database. Data exports and the health checks still use the pool.
+    pub fn with_repositories(mut self, repositories: Repositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    pub fn exporter(&self) -> &Arc<DataExporter> {
+        &self.exporter
+    }
+}
+
+/// Registers the schema, `state` and every route. App wide middleware is left to the caller, only
+/// `/graphql` brings its own rate limit.
+pub fn configure(state: web::Data<AppState>, c: &mut web::ServiceConfig) {
+    let metrics_enabled = state.config.metrics.enabled;
+    let rate_limit = RateLimit::new(
+        Arc::clone(&state.rate_limiter),
+        &state.config.auth.user_id_header,
+        admit_graphql,
+    );
+
+    c.data(graphql::new_schema())
+        .app_data(state)
+        .service(
+            web::resource("/graphql")
+                .route(web::post().to(graphql_route))
+                .route(web::get().to(graphql_route))
+                .wrap(rate_limit),
+        )
+        .service(
+            web::resource(format!("{}/{{id}}", DOWNLOAD_PATH))
+                .route(web::get().to(data_export_route)),
+        )
+        .service(web::resource("/playground").route(web::get().to(playground_route)))
+        .service(web::resource("/health/live").route(web::get().to(liveness_route)))
+        .service(web::resource("/health/ready").route(web::get().to(readiness_route)))
+        // kept for probes configured before /health/live existed, with its original body
+        .service(web::resource("/health_check").route(web::get().to(health_check_route)));
+    if metrics_enabled {
+        c.service(web::resource("/metrics").route(web::get().to(metrics_route)));
+    }
+}
+
+async fn liveness_route() -> actix_web::Result<HttpResponse> {
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "up" })))
+}
+
+async fn health_check_route() -> actix_web::Result<HttpResponse> {
+    Ok(HttpResponse::Ok().body("ok"))
+}
+
+async fn readiness_route(state: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    let report = health::check_ready(&state.pool, &state.config.health).await;
+    let mut builder = if report.is_up() {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    Ok(builder.json(report))
+}
+
+async fn metrics_route(state: web::Data<AppState>) -> actix_web::Result<HttpResponse> {
+    let body = metrics::render(&state.pool).map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body))
+}
+
+async fn data_export_route(
+    req: HttpRequest,
+    id: web::Path<String>,
+    state: web::Data<AppState>,
+) -> actix_web::Result<HttpResponse> {
+    let authorized_user_id = req
+        .headers()
+        .get(state.config.auth.user_id_header.as_str())
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| error::ErrorUnauthorized("unauthenticated"))?
+        .parse::<UserId>()
+        .map_err(error::ErrorBadRequest)?;
+
+    let pool = state.pool.clone();
+    let export = web::block(move || {
+        let conn = pool.get().map_err(|e| DaoError::Internal(e.to_string()))?;
+        Dao::<DataExport>::new().get(&conn, id.into_inner())
+    })
+    .await
+    .map_err(|e| match e {
+        BlockingError::Error(DaoError::NotFound) => error::ErrorNotFound("not found"),
+        e => error::ErrorInternalServerError(e),
+    })?;
+
+    // someone else's export is reported as missing rather than forbidden
+    if export.user_id != authorized_user_id {
+        return Err(error::ErrorNotFound("not found"));
+    }
+    if !export.is_downloadable(state.clock.now()) {
+        return Err(error::ErrorGone("export is not available"));
+    }
+
+    let path = state
+        .exporter
+        .path(export.file_name.as_deref().unwrap_or_default());
+    let body = web::block(move || fs::read(path))
+        .await
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .set_header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"data-export-{}.zip\"", export.id),
+        )
+        .body(body))
+}
+
+async fn playground_route() -> actix_web::Result<HttpResponse> {
+    playground_handler("/graphql", None).await
+}
+
+/// A `/graphql` request that passed the query limits, left in the request extensions by
+/// `admit_graphql`.
+struct Admitted {
+    payload: BatchPayload,
+    kinds: Vec<OperationKind>,
+}
+
+/// Runs inside the rate limiter before it takes any token: parses the request, resolves persisted
+/// queries and checks the query limits, so rejected documents don't use up the budget.
+fn admit_graphql(
+    req: &ServiceRequest,
+    body: web::Bytes,
+) -> Result<Vec<OperationKind>, HttpResponse> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .expect("AppState is registered together with the route");
+    let request_id = RequestId::of(req.request());
+
+    let mut payload = parse_payload(req, body).map_err(|e| HttpResponse::BadRequest().body(e))?;
+
+    for v in payload.payloads_mut() {
+        if let Err(e) = state.persisted_queries.resolve(v) {
+            // a miss is part of the protocol, the client retries with the full query
+            let mut builder = if e == PersistedQueryError::NotFound {
+                HttpResponse::Ok()
+            } else {
+                HttpResponse::BadRequest()
+            };
+            return Err(builder.json(request_error_body(e.code(), e.to_string(), &request_id)));
+        }
+    }
+
+    let mut kinds = vec![];
+    for v in payload.payloads() {
+        match state.limits.check(v.query(), v.operation_name.as_deref()) {
+            Ok(kind) => kinds.push(kind),
+            Err(e) => {
+                return Err(HttpResponse::BadRequest().json(request_error_body(
+                    e.code(),
+                    e.to_string(),
+                    &request_id,
+                )))
+            }
+        }
+    }
+
+    req.request().extensions_mut().insert(Admitted {
+        payload,
+        kinds: kinds.clone(),
+    });
+    Ok(kinds)
+}
+
+/// The query string of a `GET`, otherwise a JSON body or an `application/graphql` document.
+fn parse_payload(req: &ServiceRequest, body: web::Bytes) -> Result<BatchPayload, String> {
+    if req.method() == Method::GET {
+        let payload =
+            web::Query::<GetPayload>::from_query(req.query_string()).map_err(|e| e.to_string())?;
+        return Payload::try_from(payload.into_inner())
+            .map(BatchPayload::Single)
+            .map_err(|e| e.to_string());
+    }
+
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    if content_type.starts_with("application/graphql") {
+        let query = String::from_utf8(body.to_vec()).map_err(|e| e.to_string())?;
+        Ok(BatchPayload::Single(Payload::from_query(query)))
+    } else {
+        serde_json::from_slice(&body).map_err(|e| e.to_string())
+    }
+}
+
+async fn graphql_route(
+    req: HttpRequest,
+    schema: web::Data<graphql::Schema>,
+    state: web::Data<AppState>,
+) -> actix_web::Result<HttpResponse> {
+    let request_id = RequestId::of(&req);
+    let Admitted { payload, kinds } = req
+        .extensions_mut()
+        .remove::<Admitted>()
+        .ok_or_else(|| error::ErrorInternalServerError("request skipped the rate limit"))?;
+
+    let authorized_user_id: Option<UserId> =
+        match req.headers().get(state.config.auth.user_id_header.as_str()) {
+            Some(v) => Some(
+                v.to_str()
+                    .map_err(|e| error::ErrorBadRequest(e))?
+                    .parse()
+                    .map_err(error::ErrorBadRequest)?,
+            ),
+            None => None,
+        };
+    let is_admin = authorized_user_id.map_or(false, |v| state.config.auth.is_admin(v));
+
+    let operation_names = payload
+        .payloads()
+        .iter()
+        .map(|v| {
+            v.operation_name
+                .clone()
+                .unwrap_or_else(|| "anonymous".to_string())
+        })
+        .collect::<Vec<_>>();
+    let operation_types = kinds.iter().map(OperationKind::as_str).collect::<Vec<_>>();
+
+    let repositories = match &state.repositories {
+        Some(v) => v.clone(),
+        None => {
+            let pool = state.pool.clone();
+            let connection = web::block(move || pool.get())
+                .await
+                .map_err(error::ErrorServiceUnavailable)?;
+            Repositories::sql(connection)
+        }
+    };
+
+    let span = info_span!(
+        "graphql_operation",
+        operation_name = %operation_names.join(","),
+        operation_type = %operation_types.join(",")
+    );
+
+    let context = graphql::Context::new(
+        authorized_user_id,
+        is_admin,
+        request_id.clone(),
+        repositories,
+        Arc::clone(&state.exporter),
+        Arc::clone(&state.clock),
+        Arc::clone(&state.ids),
+    );
+    let request = payload.into_request();
+    let started_at = Instant::now();
+    let response = request
+        .execute(&schema, &context)
+        .instrument(span.clone())
+        .await;
+    let elapsed = started_at.elapsed();
+    span.in_scope(|| info!(ok = response.is_ok(), "operation executed"));
+    for (name, kind) in operation_names.iter().zip(&operation_types) {
+        metrics::observe_operation(name, kind, response.is_ok(), elapsed);
+    }
+
+    let mut body = serde_json::to_value(&response)?;
+    with_request_id(&mut body, &request_id);
+    let mut builder = if response.is_ok() {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::BadRequest()
+    };
+    Ok(builder.json(body))
+}
@@ -0,0 +1,136 @@
+use crate::config::{LogFormat, LoggingConfig};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{HeaderName, HeaderValue};
+use actix_web::{Error, HttpMessage, HttpRequest};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tracing::{field, info, info_span, warn, Instrument};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+pub fn init(config: &LoggingConfig) {
+    let builder = tracing_subscriber::fmt().with_env_filter(EnvFilter::new(&config.level));
+
+    match config.format {
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+        LogFormat::Pretty => builder.pretty().init(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn of(req: &HttpRequest) -> String {
+        req.extensions()
+            .get::<RequestId>()
+            .map(|v| v.0.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Opens an `http_request` span per request and echoes a generated `x-request-id` in the response.
+pub struct RequestTracing {
+    user_id_header: String,
+}
+
+impl RequestTracing {
+    pub fn new(user_id_header: &str) -> Self {
+        RequestTracing {
+            user_id_header: user_id_header.to_string(),
+        }
+    }
+}
+
+impl<S, B> Transform<S> for RequestTracing
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestTracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware {
+            service,
+            user_id_header: self.user_id_header.clone(),
+        }))
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: S,
+    user_id_header: String,
+}
+
+impl<S, B> Service for RequestTracingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let request_id = Uuid::new_v4().to_string();
+        let user_id = req
+            .headers()
+            .get(self.user_id_header.as_str())
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let span = info_span!(
+            "http_request",
+            request_id = %request_id,
+            method = %req.method(),
+            path = %req.path(),
+            user_id = %user_id,
+            status = field::Empty,
+            latency_ms = field::Empty
+        );
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        let started_at = Instant::now();
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(async move {
+            let result = fut.instrument(span.clone()).await;
+            span.record("latency_ms", &(started_at.elapsed().as_millis() as u64));
+
+            let mut res = match result {
+                Ok(v) => v,
+                Err(e) => {
+                    span.in_scope(|| warn!(error = %e, "request failed"));
+                    return Err(e);
+                }
+            };
+
+            if let Ok(v) = HeaderValue::from_str(&request_id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), v);
+            }
+            span.record("status", &u64::from(res.status().as_u16()));
+            span.in_scope(|| info!("request completed"));
+
+            Ok(res)
+        })
+    }
+}
@@ -0,0 +1,65 @@
+//! The seed factories with `create(&app)`, inserting into whatever the app serves from. Every
+//! fixture is created a second after the previous one, so later ones sort as newer.
+
+use super::TestApp;
+use chrono::Duration;
+use rust_graphql_diesel_sample::domain::data_export::DataExport;
+use rust_graphql_diesel_sample::domain::photo::Photo;
+use rust_graphql_diesel_sample::domain::user::User;
+use rust_graphql_diesel_sample::seed;
+
+pub struct UserFactory(seed::UserFactory);
+
+impl UserFactory {
+    pub fn new(name: &str) -> Self {
+        UserFactory(seed::UserFactory::new(name))
+    }
+
+    pub fn create(self, app: &TestApp) -> User {
+        app.clock.advance(Duration::seconds(1));
+        let user = self.0.build(app.ids.as_ref(), app.clock.as_ref());
+        app.repositories()
+            .users
+            .insert(&user)
+            .expect("failed to insert user");
+        user
+    }
+}
+
+pub struct PhotoFactory(seed::PhotoFactory);
+
+impl PhotoFactory {
+    /// A public photo of `user`.
+    pub fn new(user: &User) -> Self {
+        PhotoFactory(seed::PhotoFactory::new(user))
+    }
+
+    pub fn url(self, url: &str) -> Self {
+        PhotoFactory(self.0.url(url))
+    }
+
+    pub fn private(self) -> Self {
+        PhotoFactory(self.0.private())
+    }
+
+    pub fn create(self, app: &TestApp) -> Photo {
+        app.clock.advance(Duration::seconds(1));
+        let photo = self.0.build(app.ids.as_ref(), app.clock.as_ref());
+        app.repositories()
+            .photos
+            .insert(&photo)
+            .expect("failed to insert photo");
+        photo
+    }
+}
+
+/// A pending export of `user`, which blocks further export requests.
+pub fn pending_data_export(app: &TestApp, user: &User) -> DataExport {
+    app.clock.advance(Duration::seconds(1));
+    let export = DataExport::new(user.id, app.ids.as_ref(), app.clock.as_ref());
+    app.repositories()
+        .data_exports
+        .insert(&export)
+        .expect("failed to insert data export");
+    export
+}
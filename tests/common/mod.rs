@@ -0,0 +1,239 @@
+#![allow(dead_code)]
+
+//! In-process app and throwaway database shared by the integration tests.
+//!
+//! With the `sqlite` feature every app gets its own `:memory:` database. MySQL and Postgres need
+//! `TEST_DATABASE_URL`, which is migrated once and then only used inside a transaction that is
+//! never committed.
+
+use actix_web::http::HeaderMap;
+use actix_web::{test, web, App};
+use chrono::{TimeZone, Utc};
+use diesel::r2d2::{self, ConnectionManager, CustomizeConnection};
+use rust_graphql_diesel_sample::config::Config;
+use rust_graphql_diesel_sample::ddb::{self, DbConnection};
+use rust_graphql_diesel_sample::domain::clock::FixedClock;
+use rust_graphql_diesel_sample::domain::id::{SequentialIdGenerator, UserId};
+use rust_graphql_diesel_sample::export::DataExporter;
+use rust_graphql_diesel_sample::repository::Repositories;
+use rust_graphql_diesel_sample::server::{self, AppState};
+use rust_graphql_diesel_sample::telemetry::RequestTracing;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::env;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub mod factories;
+pub mod snapshot;
+
+/// Listed in `auth.admin_user_ids`, no such user exists.
+pub const ADMIN_ID: &str = "00000000-0000-0000-0000-0000000000ad";
+
+pub struct TestApp {
+    state: web::Data<AppState>,
+    pool: ddb::Pool,
+    /// Set by `in_memory`, served instead of the test database.
+    memory: Option<Repositories>,
+    /// Shared with the app, so fixtures and mutations get predictable timestamps.
+    pub clock: Arc<FixedClock>,
+    /// Shared with the app, ids are `...0001`, `...0002` and so on in creation order.
+    pub ids: Arc<SequentialIdGenerator>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub status: u16,
+    #[serde(skip)]
+    pub headers: HeaderMap,
+    /// `null` when the body isn't JSON.
+    pub body: Value,
+    #[serde(skip)]
+    pub text: String,
+}
+
+impl Response {
+    /// The field `name` of `data`, e.g. the payload of a mutation.
+    pub fn data(&self, name: &str) -> &Value {
+        &self.body["data"][name]
+    }
+
+    /// The `code` extension of the first error, `null` without errors.
+    pub fn error_code(&self) -> &Value {
+        &self.body["errors"][0]["extensions"]["code"]
+    }
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::with_config(|_| {})
+    }
+
+    /// Like `new`, with `configure` applied on top of the test config.
+    pub fn with_config(configure: impl FnOnce(&mut Config)) -> Self {
+        let url = database_url();
+        let pool = r2d2::Pool::builder()
+            // a single connection, so the app and the factories see the same uncommitted data
+            .max_size(1)
+            .connection_customizer(Box::new(Throwaway))
+            .build(ConnectionManager::<DbConnection>::new(url.as_str()))
+            .expect("failed to connect to the test database");
+
+        Self::build(url, pool, None, configure)
+    }
+
+    /// GraphQL served from `Repositories::in_memory`, fixtures included. The pool never connects,
+    /// so data exports and `connection` fail.
+    pub fn in_memory() -> Self {
+        let url = "unused".to_string();
+        let pool = r2d2::Pool::builder()
+            .max_size(1)
+            .min_idle(Some(0))
+            .connection_timeout(std::time::Duration::from_secs(1))
+            .build_unchecked(ConnectionManager::<DbConnection>::new(url.as_str()));
+
+        Self::build(url, pool, Some(Repositories::in_memory()), |_| {})
+    }
+
+    fn build(
+        url: String,
+        pool: ddb::Pool,
+        memory: Option<Repositories>,
+        configure: impl FnOnce(&mut Config),
+    ) -> Self {
+        let mut config = Config::defaults().expect("invalid default config");
+        config.database.url = url;
+        config.database.pool_size = 1;
+        config.auth.admin_user_ids = vec![ADMIN_ID.to_string()];
+        config.exports.dir = env::temp_dir()
+            .join(format!("exports-{}", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        configure(&mut config);
+        let clock = Arc::new(FixedClock::new(Utc.ymd(2021, 1, 1).and_hms(0, 0, 0)));
+        let ids = Arc::new(SequentialIdGenerator::new());
+        let mut state = AppState::new(config, pool.clone(), clock.clone(), ids.clone())
+            .expect("failed to build the app state");
+        if let Some(v) = &memory {
+            state = state.with_repositories(v.clone());
+        }
+
+        TestApp {
+            state: web::Data::new(state),
+            pool,
+            memory,
+            clock,
+            ids,
+        }
+    }
+
+    pub fn exporter(&self) -> &Arc<DataExporter> {
+        self.state.exporter()
+    }
+
+    pub fn connection(&self) -> ddb::Connection {
+        self.pool
+            .get()
+            .expect("failed to get a test database connection")
+    }
+
+    /// What the app serves from, for fixtures.
+    pub fn repositories(&self) -> Repositories {
+        match &self.memory {
+            Some(v) => v.clone(),
+            None => Repositories::sql(self.connection()),
+        }
+    }
+
+    /// Sends `req` through the app, wrapped in the same middleware as in `main`.
+    pub async fn call(&self, req: test::TestRequest) -> Response {
+        let state = self.state.clone();
+        let mut app = test::init_service(
+            App::new()
+                .wrap(state.config.cors.build(&state.config.auth.user_id_header))
+                .wrap(RequestTracing::new(&state.config.auth.user_id_header))
+                .configure(|c| server::configure(state, c)),
+        )
+        .await;
+
+        let res = test::call_service(&mut app, req.to_request()).await;
+        let status = res.status().as_u16();
+        let headers = res.headers().clone();
+        let body = test::read_body(res).await;
+        Response {
+            status,
+            headers,
+            body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+            text: String::from_utf8_lossy(&body).into_owned(),
+        }
+    }
+
+    /// Posts a GraphQL document to `/graphql`, authenticated as `user_id` when given.
+    pub async fn graphql(
+        &self,
+        user_id: Option<UserId>,
+        query: &str,
+        variables: Value,
+    ) -> Response {
+        let mut req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&json!({ "query": query, "variables": variables }));
+        if let Some(v) = user_id {
+            req = req.header(
+                self.state.config.auth.user_id_header.as_str(),
+                v.to_string(),
+            );
+        }
+
+        self.call(req).await
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn database_url() -> String {
+    ":memory:".to_string()
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn database_url() -> String {
+    use diesel::Connection;
+    use std::sync::Once;
+
+    static MIGRATE: Once = Once::new();
+
+    let url = env::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL must point at a database the tests may migrate");
+    MIGRATE.call_once(|| {
+        let conn = DbConnection::establish(&url).expect("failed to connect to the test database");
+        ddb::migrations::run_pending(&conn, &mut std::io::sink())
+            .expect("failed to migrate the test database");
+    });
+    url
+}
+
+#[derive(Debug)]
+struct Throwaway;
+
+impl CustomizeConnection<DbConnection, r2d2::Error> for Throwaway {
+    fn on_acquire(&self, conn: &mut DbConnection) -> Result<(), r2d2::Error> {
+        prepare(conn).map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// A fresh in-memory database per connection, migrated before use.
+#[cfg(feature = "sqlite")]
+fn prepare(conn: &DbConnection) -> diesel::QueryResult<()> {
+    ddb::setup_session(conn)?;
+    ddb::migrations::run_pending(conn, &mut std::io::sink())
+        .expect("failed to migrate the test database");
+    Ok(())
+}
+
+/// Everything runs inside a transaction that is rolled back when the connection closes.
+#[cfg(not(feature = "sqlite"))]
+fn prepare(conn: &DbConnection) -> diesel::QueryResult<()> {
+    use diesel::Connection;
+
+    ddb::setup_session(conn)?;
+    conn.begin_test_transaction()
+}
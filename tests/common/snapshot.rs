@@ -0,0 +1,60 @@
+//! JSON snapshots under `tests/snapshots`.
+//!
+//! A missing or different snapshot fails the test. Only `UPDATE_SNAPSHOTS=1` records them, so a
+//! snapshot is never written by accident.
+
+use super::Response;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn assert_snapshot(name: &str, response: &Response) {
+    let actual = serde_json::to_string_pretty(&json!({
+        "status": response.status,
+        "body": redact(&response.body),
+    }))
+    .unwrap()
+        + "\n";
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.json", name));
+    if env::var("UPDATE_SNAPSHOTS").map_or(false, |v| v == "1") {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    match fs::read_to_string(&path) {
+        Ok(expected) => assert_eq!(
+            expected, actual,
+            "snapshot {} changed, rerun with UPDATE_SNAPSHOTS=1 to accept it",
+            name
+        ),
+        Err(_) => panic!(
+            "missing snapshot {}, run with UPDATE_SNAPSHOTS=1 to record it",
+            path.display()
+        ),
+    }
+}
+
+/// Replaces request ids, the only values the test clock and id generator don't control.
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Array(v) => Value::Array(v.iter().map(redact).collect()),
+        Value::Object(v) => Value::Object(
+            v.iter()
+                .map(|(k, v)| {
+                    let v = if k == "requestId" {
+                        json!("[request-id]")
+                    } else {
+                        redact(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        v => v.clone(),
+    }
+}
@@ -0,0 +1,140 @@
+//! Preflight requests against `/graphql` with the origins from `cors.allowed_origins`.
+
+mod common;
+
+use actix_web::http::{header, Method};
+use actix_web::test::TestRequest;
+use common::{Response, TestApp};
+use rust_graphql_diesel_sample::config::{Config, ConfigError};
+
+const ORIGIN: &str = "https://app.example.com";
+
+fn preflight_request(origin: &str) -> TestRequest {
+    TestRequest::with_uri("/graphql")
+        .method(Method::OPTIONS)
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+}
+
+async fn preflight(app: &TestApp, origin: &str) -> Response {
+    app.call(preflight_request(origin)).await
+}
+
+fn allowing(origins: &[&str]) -> TestApp {
+    TestApp::with_config(|c| {
+        c.cors.allowed_origins = origins.iter().map(|v| v.to_string()).collect()
+    })
+}
+
+#[actix_rt::test]
+async fn preflight_from_an_allowed_origin() {
+    let app = allowing(&[ORIGIN]);
+
+    let res = preflight(&app, ORIGIN).await;
+
+    assert_eq!(res.status, 200);
+    assert_eq!(
+        res.headers
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .unwrap(),
+        ORIGIN
+    );
+    assert!(res
+        .headers
+        .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("POST"));
+}
+
+#[actix_rt::test]
+async fn preflight_from_another_origin() {
+    let app = allowing(&[ORIGIN]);
+
+    let res = preflight(&app, "https://evil.example.com").await;
+
+    assert_eq!(res.status, 400);
+    assert!(res
+        .headers
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+}
+
+#[actix_rt::test]
+async fn preflight_without_allowed_origins() {
+    let app = allowing(&[]);
+
+    let res = preflight(&app, ORIGIN).await;
+
+    assert_eq!(res.status, 400);
+    assert!(res
+        .headers
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+}
+
+#[actix_rt::test]
+async fn preflight_with_credentials() {
+    let app = TestApp::with_config(|c| {
+        c.cors.allowed_origins = vec![ORIGIN.to_string()];
+        c.cors.allow_credentials = true;
+    });
+
+    let res = preflight(&app, ORIGIN).await;
+
+    assert_eq!(res.status, 200);
+    assert_eq!(
+        res.headers
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .unwrap(),
+        "true"
+    );
+}
+
+#[actix_rt::test]
+async fn preflight_allows_a_renamed_auth_header() {
+    let app = TestApp::with_config(|c| {
+        c.cors.allowed_origins = vec![ORIGIN.to_string()];
+        c.auth.user_id_header = "x-auth-user".to_string();
+    });
+
+    let res = app
+        .call(preflight_request(ORIGIN).header(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            "content-type,x-auth-user",
+        ))
+        .await;
+
+    assert_eq!(res.status, 200);
+    assert!(res
+        .headers
+        .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("x-auth-user"));
+}
+
+fn config() -> Config {
+    let mut config = Config::defaults().expect("invalid default config");
+    config.database.url = "unused".to_string();
+    config
+}
+
+#[test]
+fn any_origin_is_valid_without_credentials() {
+    let mut config = config();
+    config.cors.allowed_origins = vec!["*".to_string()];
+
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn any_origin_with_credentials_is_rejected() {
+    let mut config = config();
+    config.cors.allowed_origins = vec!["*".to_string()];
+    config.cors.allow_credentials = true;
+
+    assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+}
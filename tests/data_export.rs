@@ -0,0 +1,100 @@
+//! `DataExporter::sweep`, which cleans up after exports that never finished or have expired.
+
+mod common;
+
+use chrono::Duration;
+use common::factories::{pending_data_export, UserFactory};
+use common::TestApp;
+use rust_graphql_diesel_sample::ddb::Dao;
+use rust_graphql_diesel_sample::domain::clock::Clock;
+use rust_graphql_diesel_sample::domain::data_export::{DataExport, DataExportStatus};
+use rust_graphql_diesel_sample::export::SweepReport;
+use serde_json::json;
+use std::fs;
+
+fn get(app: &TestApp, export: &DataExport) -> DataExport {
+    Dao::<DataExport>::new()
+        .get(&app.connection(), export.id.clone())
+        .expect("failed to load data export")
+}
+
+/// A completed export of `file_name`, written to the exports directory.
+fn create_completed_data_export(app: &TestApp, file_name: &str) -> DataExport {
+    let alice = UserFactory::new("alice").create(&app);
+    let mut export = pending_data_export(&app, &alice);
+
+    let path = app.exporter().path(file_name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, b"archive").unwrap();
+    export.complete(file_name.to_string(), Duration::hours(24), app.clock.now());
+    Dao::<DataExport>::new()
+        .update(&app.connection(), &export)
+        .expect("failed to update data export");
+    export
+}
+
+#[actix_rt::test]
+async fn sweep_fails_stale_exports() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let stale = pending_data_export(&app, &alice);
+    app.clock.advance(Duration::minutes(31));
+    let bob = UserFactory::new("bob").create(&app);
+    let fresh = pending_data_export(&app, &bob);
+
+    let report = app.exporter().sweep().unwrap();
+
+    assert_eq!(
+        report,
+        SweepReport {
+            failed: 1,
+            expired: 0
+        }
+    );
+    assert_eq!(get(&app, &stale).status, DataExportStatus::Failed);
+    assert_eq!(get(&app, &fresh).status, DataExportStatus::Pending);
+
+    // no longer blocks a new request with CONFLICT
+    let res = app
+        .graphql(
+            Some(alice.id),
+            "mutation { requestDataExport { status } }",
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.data("requestDataExport")["status"], json!("PENDING"));
+}
+
+#[test]
+fn sweep_deletes_expired_archives() {
+    let app = TestApp::new();
+    let export = create_completed_data_export(&app, "expired.zip");
+    app.clock.advance(Duration::hours(24));
+
+    let report = app.exporter().sweep().unwrap();
+
+    assert_eq!(
+        report,
+        SweepReport {
+            failed: 0,
+            expired: 1
+        }
+    );
+    assert!(!app.exporter().path("expired.zip").exists());
+    let export = get(&app, &export);
+    assert_eq!(export.status, DataExportStatus::Completed);
+    assert_eq!(export.file_name, None);
+}
+
+#[test]
+fn sweep_keeps_archives_until_they_expire() {
+    let app = TestApp::new();
+    let export = create_completed_data_export(&app, "current.zip");
+    app.clock.advance(Duration::hours(23));
+
+    let report = app.exporter().sweep().unwrap();
+
+    assert_eq!(report, SweepReport::default());
+    assert!(app.exporter().path("current.zip").exists());
+    assert_eq!(get(&app, &export).file_name.as_deref(), Some("current.zip"));
+}
@@ -0,0 +1,754 @@
+//! Every `Query` and `Mutation` field against the in-process app, snapshotted in `tests/snapshots`.
+//!
+//! Each field has a test named `query_<field>` or `mutation_<field>` in snake case, which
+//! `every_schema_field_is_covered` checks against `schema.graphql`.
+
+mod common;
+
+use common::factories::{pending_data_export, PhotoFactory, UserFactory};
+use common::snapshot::assert_snapshot;
+use common::{TestApp, ADMIN_ID};
+use convert_case::{Case, Casing};
+use serde_json::json;
+
+const UNKNOWN_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+const ME: &str = r#"
+query {
+    me {
+        id
+        name
+        version
+        createdAt
+        updatedAt
+        photos { id url isPublic version }
+        loadPhotos { id }
+        dataExports { id status formatVersion downloadUrl error }
+    }
+}
+"#;
+
+const OTHERS: &str = r#"
+query Others($filter: UserFilter, $orderBy: UserOrderBy) {
+    others(filter: $filter, orderBy: $orderBy) {
+        edges { node { id name createdAt updatedAt photos { id isPublic } loadPhotos { id } } }
+    }
+}
+"#;
+
+const ALL_USERS: &str = r#"
+query {
+    allUsers {
+        edges { node { id name photos { id } } }
+    }
+}
+"#;
+
+const PHOTOS: &str = r#"
+query Photos($filter: PhotoFilter, $orderBy: PhotoOrderBy) {
+    photos(filter: $filter, orderBy: $orderBy) {
+        edges { node { id userId url isPublic version } }
+    }
+}
+"#;
+
+const PHOTO: &str = r#"
+query Photo($id: PhotoId!) {
+    photo(id: $id) {
+        id userId url isPublic version createdAt updatedAt
+        loadUser { id name }
+    }
+}
+"#;
+
+const ALL_PHOTOS: &str = r#"
+query AllPhotos($filter: PhotoFilter, $orderBy: PhotoOrderBy) {
+    allPhotos(filter: $filter, orderBy: $orderBy) {
+        edges { node { id url isPublic user { id name } loadUser { id name } } }
+    }
+}
+"#;
+
+const AUDIT_EVENTS: &str = r#"
+query AuditEvents($filter: AuditEventFilter, $first: Int, $after: String) {
+    auditEvents(filter: $filter, first: $first, after: $after) {
+        edges {
+            node {
+                id actorId action entityType entityId
+                changes { field before after }
+                requestId createdAt
+            }
+            cursor
+        }
+        pageInfo { hasNextPage endCursor }
+    }
+}
+"#;
+
+const SIGN_UP: &str = r#"
+mutation SignUp($input: SignUpInput!) {
+    signUp(input: $input) { id name version photos { id } }
+}
+"#;
+
+const UPDATE_USER: &str = r#"
+mutation UpdateUser($input: UpdateUserInput!) {
+    updateUser(input: $input) { id name version }
+}
+"#;
+
+const LEAVE: &str = r#"
+mutation {
+    leave
+}
+"#;
+
+const CREATE_PHOTO: &str = r#"
+mutation CreatePhoto($input: CreatePhotoInput!) {
+    createPhoto(input: $input) { id userId url isPublic version }
+}
+"#;
+
+const UPDATE_PHOTO: &str = r#"
+mutation UpdatePhoto($input: UpdatePhotoInput!) {
+    updatePhoto(input: $input) { id url isPublic version }
+}
+"#;
+
+const DELETE_PHOTO: &str = r#"
+mutation DeletePhoto($input: DeletePhotoInput!) {
+    deletePhoto(input: $input)
+}
+"#;
+
+const UPDATE_PHOTOS: &str = r#"
+mutation UpdatePhotos($ids: [PhotoId!]!, $isPublic: Boolean!) {
+    updatePhotos(ids: $ids, isPublic: $isPublic) { succeeded forbidden notFound }
+}
+"#;
+
+const DELETE_PHOTOS: &str = r#"
+mutation DeletePhotos($ids: [PhotoId!]!) {
+    deletePhotos(ids: $ids) { succeeded forbidden notFound }
+}
+"#;
+
+const REQUEST_DATA_EXPORT: &str = r#"
+mutation {
+    requestDataExport { id status formatVersion downloadUrl error }
+}
+"#;
+
+const IMPORT_PHOTOS: &str = r#"
+mutation ImportPhotos($input: [CreatePhotoInput!]!) {
+    importPhotos(input: $input) {
+        imported
+        failed
+        rows { index photo { id url isPublic } error }
+    }
+}
+"#;
+
+#[test]
+fn every_schema_field_is_covered() {
+    let schema = include_str!("../src/graphql/schema.graphql");
+    let tests = include_str!("graphql.rs");
+
+    for (kind, type_name) in &[("query", "Query"), ("mutation", "Mutation")] {
+        let fields = schema
+            .lines()
+            .skip_while(|v| v.trim() != format!("type {} {{", type_name))
+            .skip(1)
+            .take_while(|v| v.trim() != "}")
+            .filter_map(|v| v.trim().split(|c| c == '(' || c == ':').next())
+            .filter(|v| !v.is_empty());
+
+        for field in fields {
+            let test_name = format!("async fn {}_{}(", kind, field.to_case(Case::Snake));
+            assert!(
+                tests.contains(&test_name),
+                "{}.{} has no `{}` test",
+                type_name,
+                field,
+                test_name
+            );
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn query_me() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    PhotoFactory::new(&alice).create(&app);
+    PhotoFactory::new(&alice).private().create(&app);
+    pending_data_export(&app, &alice);
+
+    let res = app.graphql(Some(alice.id), ME, json!({})).await;
+    assert_snapshot("query_me", &res);
+}
+
+#[actix_rt::test]
+async fn query_me_requires_authentication() {
+    let app = TestApp::new();
+
+    let res = app.graphql(None, ME, json!({})).await;
+    assert_eq!(res.error_code(), "UN_AUTHENTICATE");
+    assert_snapshot("query_me_requires_authentication", &res);
+}
+
+#[actix_rt::test]
+async fn query_me_of_unknown_user() {
+    let app = TestApp::new();
+
+    let res = app
+        .graphql(Some(UNKNOWN_ID.parse().unwrap()), ME, json!({}))
+        .await;
+    assert_eq!(res.error_code(), "NOT_FOUND");
+    assert_snapshot("query_me_of_unknown_user", &res);
+}
+
+#[actix_rt::test]
+async fn query_others() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    let carol = UserFactory::new("carol").create(&app);
+    PhotoFactory::new(&bob).create(&app);
+    PhotoFactory::new(&bob).private().create(&app);
+    PhotoFactory::new(&carol).create(&app);
+
+    let res = app.graphql(Some(alice.id), OTHERS, json!({})).await;
+    assert_snapshot("query_others", &res);
+}
+
+#[actix_rt::test]
+async fn query_others_filtered_and_ordered() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    UserFactory::new("Robert").create(&app);
+    UserFactory::new("bob").create(&app);
+    UserFactory::new("carol").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            OTHERS,
+            json!({
+                "filter": { "nameContains": "B" },
+                "orderBy": { "field": "NAME", "direction": "ASC" },
+            }),
+        )
+        .await;
+    assert_snapshot("query_others_filtered_and_ordered", &res);
+}
+
+#[actix_rt::test]
+async fn query_all_users() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    PhotoFactory::new(&alice).create(&app);
+    PhotoFactory::new(&bob).create(&app);
+
+    let res = app.graphql(Some(alice.id), ALL_USERS, json!({})).await;
+    assert_snapshot("query_all_users", &res);
+}
+
+#[actix_rt::test]
+async fn query_photos() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    PhotoFactory::new(&alice).create(&app);
+    PhotoFactory::new(&alice).private().create(&app);
+    PhotoFactory::new(&bob).create(&app);
+
+    let res = app.graphql(Some(alice.id), PHOTOS, json!({})).await;
+    assert_snapshot("query_photos", &res);
+}
+
+#[actix_rt::test]
+async fn query_photos_filtered_and_ordered() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    PhotoFactory::new(&alice).create(&app);
+    PhotoFactory::new(&alice).private().create(&app);
+    PhotoFactory::new(&alice).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            PHOTOS,
+            json!({
+                "filter": { "isPublic": true },
+                "orderBy": { "field": "CREATED_AT", "direction": "ASC" },
+            }),
+        )
+        .await;
+    assert_snapshot("query_photos_filtered_and_ordered", &res);
+}
+
+#[actix_rt::test]
+async fn query_photo() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let photo = PhotoFactory::new(&alice).private().create(&app);
+
+    let res = app
+        .graphql(Some(alice.id), PHOTO, json!({ "id": photo.id.to_string() }))
+        .await;
+    assert_snapshot("query_photo", &res);
+}
+
+#[actix_rt::test]
+async fn query_photo_of_another_user() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    let photo = PhotoFactory::new(&bob).private().create(&app);
+
+    let res = app
+        .graphql(Some(alice.id), PHOTO, json!({ "id": photo.id.to_string() }))
+        .await;
+    assert_eq!(res.error_code(), "FORBIDDEN");
+    assert_snapshot("query_photo_of_another_user", &res);
+}
+
+#[actix_rt::test]
+async fn query_photo_not_found() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(Some(alice.id), PHOTO, json!({ "id": UNKNOWN_ID }))
+        .await;
+    assert_eq!(res.error_code(), "NOT_FOUND");
+    assert_snapshot("query_photo_not_found", &res);
+}
+
+#[actix_rt::test]
+async fn query_photo_with_malformed_id() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(Some(alice.id), PHOTO, json!({ "id": "not-a-uuid" }))
+        .await;
+    assert_eq!(res.error_code(), "BAD_REQUEST");
+    assert_snapshot("query_photo_with_malformed_id", &res);
+}
+
+#[actix_rt::test]
+async fn query_all_photos() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    PhotoFactory::new(&alice).create(&app);
+    PhotoFactory::new(&bob).create(&app);
+    PhotoFactory::new(&bob).private().create(&app);
+
+    let res = app.graphql(Some(alice.id), ALL_PHOTOS, json!({})).await;
+    assert_snapshot("query_all_photos", &res);
+}
+
+#[actix_rt::test]
+async fn query_all_photos_filtered_by_user() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    PhotoFactory::new(&alice).create(&app);
+    PhotoFactory::new(&bob).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            ALL_PHOTOS,
+            json!({ "filter": { "userId": bob.id.to_string() } }),
+        )
+        .await;
+    assert_snapshot("query_all_photos_filtered_by_user", &res);
+}
+
+#[actix_rt::test]
+async fn query_audit_events() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    let photo = PhotoFactory::new(&alice).create(&app);
+    app.graphql(
+        Some(alice.id),
+        UPDATE_PHOTO,
+        json!({ "input": { "id": photo.id.to_string(), "isPublic": false } }),
+    )
+    .await;
+    app.graphql(
+        Some(alice.id),
+        DELETE_PHOTO,
+        json!({ "input": { "id": photo.id.to_string() } }),
+    )
+    .await;
+    app.graphql(
+        Some(bob.id),
+        UPDATE_USER,
+        json!({ "input": { "name": "robert" } }),
+    )
+    .await;
+
+    let res = app
+        .graphql(
+            Some(ADMIN_ID.parse().unwrap()),
+            AUDIT_EVENTS,
+            json!({ "filter": { "actorId": alice.id.to_string() } }),
+        )
+        .await;
+    assert_snapshot("query_audit_events", &res);
+}
+
+#[actix_rt::test]
+async fn query_audit_events_paginated() {
+    let app = TestApp::new();
+    let admin = ADMIN_ID.parse().unwrap();
+    let alice = UserFactory::new("alice").create(&app);
+    for i in 0..3 {
+        app.graphql(
+            Some(alice.id),
+            UPDATE_USER,
+            json!({ "input": { "name": format!("alice{}", i) } }),
+        )
+        .await;
+    }
+    let all = app.graphql(Some(admin), AUDIT_EVENTS, json!({})).await;
+    let all = all.data("auditEvents")["edges"].as_array().unwrap().clone();
+    assert_eq!(all.len(), 3);
+
+    let first = app
+        .graphql(Some(admin), AUDIT_EVENTS, json!({ "first": 2 }))
+        .await;
+    let page = first.data("auditEvents");
+    assert_eq!(page["edges"], json!(all[..2]));
+    assert_eq!(page["pageInfo"]["hasNextPage"], json!(true));
+    assert_eq!(page["pageInfo"]["endCursor"], all[1]["cursor"]);
+
+    let second = app
+        .graphql(
+            Some(admin),
+            AUDIT_EVENTS,
+            json!({ "first": 2, "after": page["pageInfo"]["endCursor"] }),
+        )
+        .await;
+    let page = second.data("auditEvents");
+    assert_eq!(page["edges"], json!(all[2..]));
+    assert_eq!(page["pageInfo"]["hasNextPage"], json!(false));
+    assert_eq!(page["pageInfo"]["endCursor"], all[2]["cursor"]);
+}
+
+#[actix_rt::test]
+async fn query_audit_events_rejects_bad_pages() {
+    let app = TestApp::new();
+    let admin = ADMIN_ID.parse().unwrap();
+
+    for variables in [
+        json!({ "first": 0 }),
+        json!({ "first": 501 }),
+        json!({ "after": "not a cursor" }),
+        json!({ "after": "1609459203.000000_not-an-id" }),
+    ] {
+        let res = app.graphql(Some(admin), AUDIT_EVENTS, variables).await;
+        assert_eq!(res.error_code(), "BAD_REQUEST");
+    }
+}
+
+#[actix_rt::test]
+async fn query_audit_events_requires_admin() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app.graphql(Some(alice.id), AUDIT_EVENTS, json!({})).await;
+    assert_eq!(res.error_code(), "FORBIDDEN");
+    assert_snapshot("query_audit_events_requires_admin", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_sign_up() {
+    let app = TestApp::new();
+
+    let res = app
+        .graphql(None, SIGN_UP, json!({ "input": { "name": "alice" } }))
+        .await;
+    assert_eq!(
+        res.data("signUp")["id"],
+        "00000000-0000-0000-0000-000000000001"
+    );
+    assert_snapshot("mutation_sign_up", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_sign_up_with_empty_name() {
+    let app = TestApp::new();
+
+    let res = app
+        .graphql(None, SIGN_UP, json!({ "input": { "name": "" } }))
+        .await;
+    assert_eq!(res.error_code(), "BAD_REQUEST");
+    assert_snapshot("mutation_sign_up_with_empty_name", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_user() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            UPDATE_USER,
+            json!({ "input": { "name": "alicia", "expectedVersion": alice.version } }),
+        )
+        .await;
+    assert_snapshot("mutation_update_user", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_user_with_stale_version() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            UPDATE_USER,
+            json!({ "input": { "name": "alicia", "expectedVersion": alice.version + 1 } }),
+        )
+        .await;
+    assert_eq!(res.error_code(), "CONFLICT");
+    assert_snapshot("mutation_update_user_with_stale_version", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_user_requires_authentication() {
+    let app = TestApp::new();
+
+    let res = app
+        .graphql(None, UPDATE_USER, json!({ "input": { "name": "alicia" } }))
+        .await;
+    assert_eq!(res.error_code(), "UN_AUTHENTICATE");
+    assert_snapshot("mutation_update_user_requires_authentication", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_leave() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app.graphql(Some(alice.id), LEAVE, json!({})).await;
+    assert_snapshot("mutation_leave", &res);
+
+    let res = app.graphql(Some(alice.id), ME, json!({})).await;
+    assert_eq!(res.error_code(), "NOT_FOUND");
+    assert_snapshot("mutation_leave_then_me", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_create_photo() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            CREATE_PHOTO,
+            json!({ "input": { "url": "https://example.com/new.jpg", "isPublic": false } }),
+        )
+        .await;
+    assert_snapshot("mutation_create_photo", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_create_photo_with_empty_url() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            CREATE_PHOTO,
+            json!({ "input": { "url": "", "isPublic": true } }),
+        )
+        .await;
+    assert_eq!(res.error_code(), "BAD_REQUEST");
+    assert_snapshot("mutation_create_photo_with_empty_url", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_photo() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let photo = PhotoFactory::new(&alice).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            UPDATE_PHOTO,
+            json!({ "input": { "id": photo.id.to_string(), "isPublic": false, "expectedVersion": photo.version } }),
+        )
+        .await;
+    assert_snapshot("mutation_update_photo", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_photo_of_another_user() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    let photo = PhotoFactory::new(&bob).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            UPDATE_PHOTO,
+            json!({ "input": { "id": photo.id.to_string(), "isPublic": false } }),
+        )
+        .await;
+    assert_eq!(res.error_code(), "FORBIDDEN");
+    assert_snapshot("mutation_update_photo_of_another_user", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_photo_with_stale_version() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let photo = PhotoFactory::new(&alice).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            UPDATE_PHOTO,
+            json!({ "input": { "id": photo.id.to_string(), "isPublic": false, "expectedVersion": photo.version + 1 } }),
+        )
+        .await;
+    assert_eq!(res.error_code(), "CONFLICT");
+    assert_snapshot("mutation_update_photo_with_stale_version", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_delete_photo() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let photo = PhotoFactory::new(&alice).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            DELETE_PHOTO,
+            json!({ "input": { "id": photo.id.to_string() } }),
+        )
+        .await;
+    assert_snapshot("mutation_delete_photo", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_delete_photo_not_found() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            DELETE_PHOTO,
+            json!({ "input": { "id": UNKNOWN_ID } }),
+        )
+        .await;
+    assert_eq!(res.error_code(), "NOT_FOUND");
+    assert_snapshot("mutation_delete_photo_not_found", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_update_photos() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    let own = PhotoFactory::new(&alice).create(&app);
+    let others = PhotoFactory::new(&bob).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            UPDATE_PHOTOS,
+            json!({ "ids": [own.id.to_string(), others.id.to_string(), UNKNOWN_ID], "isPublic": false }),
+        )
+        .await;
+    assert_snapshot("mutation_update_photos", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_delete_photos() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    let bob = UserFactory::new("bob").create(&app);
+    let own = PhotoFactory::new(&alice).create(&app);
+    let others = PhotoFactory::new(&bob).create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            DELETE_PHOTOS,
+            json!({ "ids": [own.id.to_string(), others.id.to_string(), UNKNOWN_ID] }),
+        )
+        .await;
+    assert_snapshot("mutation_delete_photos", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_delete_photos_requires_authentication() {
+    let app = TestApp::new();
+
+    let res = app
+        .graphql(None, DELETE_PHOTOS, json!({ "ids": [UNKNOWN_ID] }))
+        .await;
+    assert_eq!(res.error_code(), "UN_AUTHENTICATE");
+    assert_snapshot("mutation_delete_photos_requires_authentication", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_request_data_export() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(Some(alice.id), REQUEST_DATA_EXPORT, json!({}))
+        .await;
+    assert_snapshot("mutation_request_data_export", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_request_data_export_while_one_is_pending() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+    pending_data_export(&app, &alice);
+
+    let res = app
+        .graphql(Some(alice.id), REQUEST_DATA_EXPORT, json!({}))
+        .await;
+    assert_eq!(res.error_code(), "CONFLICT");
+    assert_snapshot("mutation_request_data_export_while_one_is_pending", &res);
+}
+
+#[actix_rt::test]
+async fn mutation_import_photos() {
+    let app = TestApp::new();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(
+            Some(alice.id),
+            IMPORT_PHOTOS,
+            json!({ "input": [
+                { "url": "https://example.com/a.jpg", "isPublic": true },
+                { "url": "", "isPublic": true },
+                { "url": "https://example.com/b.jpg", "isPublic": false },
+            ] }),
+        )
+        .await;
+    assert_snapshot("mutation_import_photos", &res);
+}
@@ -0,0 +1,27 @@
+//! Probe routes that don't depend on the database schema.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use common::TestApp;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn health_check_keeps_its_plain_body() {
+    let app = TestApp::new();
+
+    let res = app.call(TestRequest::get().uri("/health_check")).await;
+
+    assert_eq!(res.status, 200);
+    assert_eq!(res.text, "ok");
+}
+
+#[actix_rt::test]
+async fn liveness() {
+    let app = TestApp::new();
+
+    let res = app.call(TestRequest::get().uri("/health/live")).await;
+
+    assert_eq!(res.status, 200);
+    assert_eq!(res.body, json!({ "status": "up" }));
+}
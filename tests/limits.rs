@@ -0,0 +1,198 @@
+//! `QueryLimits` against documents written for `schema.graphql`, without running the app.
+
+use rust_graphql_diesel_sample::graphql::limits::{LimitError, QueryLimits};
+use rust_graphql_diesel_sample::rate_limit::OperationKind;
+
+fn limits() -> QueryLimits {
+    QueryLimits::new(15, 1000, 30)
+}
+
+#[test]
+fn depth_within_the_limit() {
+    let limits = QueryLimits::new(3, 1000, 30);
+
+    assert_eq!(
+        limits.check("{ me { photos { id } } }", None),
+        Ok(OperationKind::Query)
+    );
+}
+
+#[test]
+fn depth_over_the_limit() {
+    let limits = QueryLimits::new(3, 1000, 30);
+
+    assert_eq!(
+        limits.check("{ me { photos { loadUser { id } } } }", None),
+        Err(LimitError::TooDeep { depth: 4, max: 3 })
+    );
+}
+
+#[test]
+fn inline_fragments_count_toward_depth() {
+    let limits = QueryLimits::new(3, 1000, 30);
+
+    assert_eq!(
+        limits.check("{ me { ... on Me { photos { id } } } }", None),
+        Err(LimitError::TooDeep { depth: 4, max: 3 })
+    );
+}
+
+#[test]
+fn fragment_spreads_count_toward_depth() {
+    let limits = QueryLimits::new(3, 1000, 30);
+    let query = "{ me { ...MePhotos } } fragment MePhotos on Me { photos { id } }";
+
+    assert_eq!(
+        limits.check(query, None),
+        Err(LimitError::TooDeep { depth: 4, max: 3 })
+    );
+}
+
+/// Used to overflow the stack while parsing, before any limit was checked.
+#[test]
+fn deeply_nested_inline_fragments_are_rejected() {
+    let query = format!("{{{}{}", "...{".repeat(100_000), "}".repeat(100_001));
+
+    assert_eq!(
+        limits().check(&query, None),
+        Err(LimitError::TooDeep { depth: 16, max: 15 })
+    );
+}
+
+#[test]
+fn deeply_nested_fields_are_rejected() {
+    let query = format!("{{{}{}", "me {".repeat(100_000), "}".repeat(100_001));
+
+    assert_eq!(
+        limits().check(&query, None),
+        Err(LimitError::TooDeep { depth: 16, max: 15 })
+    );
+}
+
+#[test]
+fn list_fields_multiply_the_complexity_of_their_selection() {
+    // others 10 + edges (10 + 10 * (node 1 + photos (10 + 10 * id 1))) = 230
+    let query = "{ others { edges { node { photos { id } } } } }";
+
+    assert_eq!(
+        QueryLimits::new(15, 230, 30).check(query, None),
+        Ok(OperationKind::Query)
+    );
+    assert_eq!(
+        QueryLimits::new(15, 229, 30).check(query, None),
+        Err(LimitError::TooComplex {
+            complexity: 230,
+            max: 229
+        })
+    );
+}
+
+#[test]
+fn aliases_over_the_limit() {
+    let limits = QueryLimits::new(15, 1000, 1);
+
+    assert_eq!(
+        limits.check("{ a: me { id } }", None),
+        Ok(OperationKind::Query)
+    );
+    assert_eq!(
+        limits.check("{ a: me { id } b: me { id } }", None),
+        Err(LimitError::TooManyAliases { aliases: 2, max: 1 })
+    );
+}
+
+#[test]
+fn fragment_cycles_are_rejected() {
+    let query = r#"
+        query { me { ...A } }
+        fragment A on Me { ...B }
+        fragment B on Me { ...A }
+    "#;
+
+    assert_eq!(
+        limits().check(query, None),
+        Err(LimitError::Syntax("fragment A spreads itself".to_string()))
+    );
+}
+
+#[test]
+fn unknown_fragments_are_rejected() {
+    assert_eq!(
+        limits().check("{ me { ...Missing } }", None),
+        Err(LimitError::Syntax("unknown fragment Missing".to_string()))
+    );
+}
+
+#[test]
+fn only_the_named_operation_is_checked() {
+    let limits = QueryLimits::new(15, 10, 30);
+    let query = r#"
+        query Cheap { me { id } }
+        query Expensive { allPhotos { edges { node { id } } } }
+    "#;
+
+    assert_eq!(limits.check(query, Some("Cheap")), Ok(OperationKind::Query));
+    assert_eq!(
+        limits.check(query, Some("Expensive")),
+        Err(LimitError::TooComplex {
+            complexity: 50,
+            max: 10
+        })
+    );
+}
+
+#[test]
+fn mutations_are_recognised() {
+    let query = "query Me { me { id } } mutation Leave { leave }";
+
+    assert_eq!(
+        limits().check("mutation { leave }", None),
+        Ok(OperationKind::Mutation)
+    );
+    assert_eq!(
+        limits().check(query, Some("Leave")),
+        Ok(OperationKind::Mutation)
+    );
+    assert_eq!(limits().check(query, Some("Me")), Ok(OperationKind::Query));
+    assert_eq!(
+        limits().check("{ me { id } }", None),
+        Ok(OperationKind::Query)
+    );
+}
+
+#[test]
+fn unparsable_documents_are_rejected() {
+    assert!(matches!(
+        limits().check("mutation {", None),
+        Err(LimitError::Syntax(_))
+    ));
+}
+
+#[test]
+fn deeply_nested_argument_values_are_rejected() {
+    let query = format!(
+        "{{ photos(filter: {}{}) {{ edges {{ node {{ id }} }} }} }}",
+        "[".repeat(100_000),
+        "]".repeat(100_000)
+    );
+
+    assert!(matches!(
+        limits().check(&query, None),
+        Err(LimitError::Syntax(_))
+    ));
+}
+
+#[test]
+fn fields_spanning_several_lines_keep_their_cost() {
+    let query =
+        "{\n  allPhotos(\n    filter: { isPublic: true }\n  ) {\n    edges { node { id } }\n  }\n}";
+
+    // allPhotos 20 + edges (10 + 10 * (node 1 + id 1)) = 50
+    assert_eq!(
+        QueryLimits::new(15, 49, 30).check(query, None),
+        Err(LimitError::TooComplex {
+            complexity: 50,
+            max: 49
+        })
+    );
+}
@@ -0,0 +1,166 @@
+//! The resolvers on `Repositories::in_memory` against the same requests on the test database,
+//! which have to answer identically for the in-memory store to stand in for SQL in tests.
+
+mod common;
+
+use common::factories::{pending_data_export, PhotoFactory, UserFactory};
+use common::snapshot::redact;
+use common::{TestApp, ADMIN_ID};
+use serde_json::{json, Value};
+
+const USERS: &str = r#"
+query Users($filter: UserFilter, $orderBy: UserOrderBy) {
+    me { id name version photos { id url isPublic } dataExports { id status } }
+    others(filter: $filter, orderBy: $orderBy) { edges { node { id name photos { id } } } }
+    allUsers { edges { node { id name photos { id } } } }
+}
+"#;
+
+const PHOTOS: &str = r#"
+query Photos($id: PhotoId!) {
+    photos(orderBy: { field: CREATED_AT, direction: ASC }) { edges { node { id url isPublic } } }
+    allPhotos { edges { node { id isPublic user { id name } } } }
+    photo(id: $id) { id loadUser { id } }
+}
+"#;
+
+const UPDATE_USER: &str = r#"
+mutation UpdateUser($input: UpdateUserInput!) {
+    updateUser(input: $input) { id name version }
+}
+"#;
+
+const CREATE_PHOTO: &str = r#"
+mutation CreatePhoto($input: CreatePhotoInput!) {
+    createPhoto(input: $input) { id userId url isPublic version }
+}
+"#;
+
+const UPDATE_PHOTO: &str = r#"
+mutation UpdatePhoto($input: UpdatePhotoInput!) {
+    updatePhoto(input: $input) { id isPublic version }
+}
+"#;
+
+const DELETE_PHOTO: &str = r#"
+mutation DeletePhoto($input: DeletePhotoInput!) {
+    deletePhoto(input: $input)
+}
+"#;
+
+const REQUEST_DATA_EXPORT: &str = "mutation { requestDataExport { id status } }";
+
+const LEAVE: &str = "mutation { leave }";
+
+const AUDIT_EVENTS: &str = r#"
+query {
+    auditEvents(first: 2) {
+        edges {
+            node { id actorId action entityType entityId changes { field before after } createdAt }
+            cursor
+        }
+        pageInfo { hasNextPage endCursor }
+    }
+}
+"#;
+
+/// Queries and mutations covering every repository, each response redacted like a snapshot.
+async fn run(app: &TestApp) -> Vec<Value> {
+    let alice = UserFactory::new("alice").create(app);
+    let bob = UserFactory::new("bob").create(app);
+    let robert = UserFactory::new("Robert").create(app);
+    let public = PhotoFactory::new(&alice).create(app);
+    let private = PhotoFactory::new(&alice).private().create(app);
+    PhotoFactory::new(&bob).create(app);
+    pending_data_export(app, &alice);
+
+    let admin = ADMIN_ID.parse().unwrap();
+    let requests = vec![
+        (
+            Some(alice.id),
+            USERS,
+            json!({
+                "filter": { "nameContains": "B" },
+                "orderBy": { "field": "NAME", "direction": "ASC" },
+            }),
+        ),
+        (
+            Some(alice.id),
+            PHOTOS,
+            json!({ "id": private.id.to_string() }),
+        ),
+        (
+            Some(bob.id),
+            PHOTOS,
+            json!({ "id": private.id.to_string() }),
+        ),
+        (
+            Some(alice.id),
+            UPDATE_USER,
+            json!({ "input": { "name": "alicia", "expectedVersion": alice.version } }),
+        ),
+        (
+            Some(alice.id),
+            UPDATE_USER,
+            json!({ "input": { "name": "alice", "expectedVersion": alice.version } }),
+        ),
+        (
+            Some(bob.id),
+            CREATE_PHOTO,
+            json!({ "input": { "url": "https://example.com/new.jpg", "isPublic": false } }),
+        ),
+        (
+            Some(alice.id),
+            UPDATE_PHOTO,
+            json!({ "input": { "id": public.id.to_string(), "isPublic": false } }),
+        ),
+        (
+            Some(bob.id),
+            DELETE_PHOTO,
+            json!({ "input": { "id": private.id.to_string() } }),
+        ),
+        (
+            Some(alice.id),
+            DELETE_PHOTO,
+            json!({ "input": { "id": private.id.to_string() } }),
+        ),
+        (Some(alice.id), REQUEST_DATA_EXPORT, json!({})),
+        (Some(robert.id), LEAVE, json!({})),
+        (Some(alice.id), USERS, json!({})),
+        (Some(admin), AUDIT_EVENTS, json!({})),
+    ];
+
+    let mut responses = vec![];
+    for (user_id, query, variables) in requests {
+        let res = app.graphql(user_id, query, variables).await;
+        responses.push(json!({ "status": res.status, "body": redact(&res.body) }));
+    }
+    responses
+}
+
+#[actix_rt::test]
+async fn in_memory_repositories_answer_like_sql() {
+    let sql = run(&TestApp::new()).await;
+    let memory = run(&TestApp::in_memory()).await;
+
+    for (i, (sql, memory)) in sql.iter().zip(&memory).enumerate() {
+        assert_eq!(memory, sql, "response {} differs", i);
+    }
+    assert_eq!(memory.len(), sql.len());
+}
+
+#[actix_rt::test]
+async fn in_memory_fixtures_are_served() {
+    let app = TestApp::in_memory();
+    let alice = UserFactory::new("alice").create(&app);
+
+    let res = app
+        .graphql(Some(alice.id), "query { me { id name } }", json!({}))
+        .await;
+
+    assert_eq!(res.status, 200);
+    assert_eq!(
+        res.data("me"),
+        &json!({ "id": alice.id.to_string(), "name": "alice" })
+    );
+}
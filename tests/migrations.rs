@@ -0,0 +1,42 @@
+//! The embedded migrations against the migration directories of every backend.
+
+mod common;
+
+use common::TestApp;
+use rust_graphql_diesel_sample::ddb::migrations;
+use std::fs;
+use std::path::Path;
+
+const MIGRATION_SETS: [&str; 3] = ["migrations", "migrations_sqlite", "migrations_postgres"];
+
+#[test]
+fn every_migration_directory_is_embedded() {
+    let app = TestApp::new();
+    let embedded = migrations::status(&app.connection())
+        .expect("failed to read the migration status")
+        .into_iter()
+        .map(|v| v.name.to_string())
+        .collect::<Vec<_>>();
+
+    for set in &MIGRATION_SETS {
+        let mut dirs = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join(set))
+            .unwrap()
+            .map(|v| v.unwrap().path())
+            .filter(|v| v.is_dir())
+            .map(|v| v.file_name().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        dirs.sort();
+
+        assert_eq!(dirs, embedded, "{} differs from migrations::all", set);
+    }
+}
+
+#[test]
+fn every_migration_is_applied() {
+    let app = TestApp::new();
+
+    let status = migrations::status(&app.connection()).unwrap();
+
+    assert!(status.iter().all(|v| v.applied));
+    assert!(!migrations::has_pending(&app.connection()).unwrap());
+}
@@ -0,0 +1,197 @@
+//! Token buckets on their own, and the `429` the app answers once one is empty.
+
+mod common;
+
+use actix_web::http::header;
+use common::TestApp;
+use rust_graphql_diesel_sample::rate_limit::{
+    Budget, InMemoryStore, OperationKind, RateLimitStore, RateLimiter,
+};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+const ONE_PER_SECOND: Budget = Budget {
+    burst: 2,
+    per_minute: 60,
+};
+
+/// Never refills, so every call after the first is over the limit.
+const ONCE: Budget = Budget {
+    burst: 1,
+    per_minute: 0,
+};
+
+fn limiter(budget: Budget) -> RateLimiter {
+    RateLimiter::new(Box::new(InMemoryStore::default()), budget, budget, false)
+}
+
+#[test]
+fn bucket_allows_a_burst_then_waits_for_a_token() {
+    let store = InMemoryStore::default();
+    let now = Instant::now();
+
+    assert_eq!(store.acquire(&["a"], ONE_PER_SECOND, now), Ok(()));
+    assert_eq!(store.acquire(&["a"], ONE_PER_SECOND, now), Ok(()));
+    assert_eq!(
+        store.acquire(&["a"], ONE_PER_SECOND, now),
+        Err(Duration::from_secs(1))
+    );
+}
+
+#[test]
+fn bucket_refills_over_time() {
+    let store = InMemoryStore::default();
+    let now = Instant::now();
+    store.acquire(&["a"], ONE_PER_SECOND, now).unwrap();
+    store.acquire(&["a"], ONE_PER_SECOND, now).unwrap();
+
+    assert_eq!(
+        store.acquire(&["a"], ONE_PER_SECOND, now + Duration::from_millis(500)),
+        Err(Duration::from_millis(500))
+    );
+    assert_eq!(
+        store.acquire(&["a"], ONE_PER_SECOND, now + Duration::from_secs(1)),
+        Ok(())
+    );
+}
+
+#[test]
+fn bucket_refills_up_to_the_burst() {
+    let store = InMemoryStore::default();
+    let now = Instant::now();
+    store.acquire(&["a"], ONE_PER_SECOND, now).unwrap();
+
+    let later = now + Duration::from_secs(3600);
+    assert_eq!(store.acquire(&["a"], ONE_PER_SECOND, later), Ok(()));
+    assert_eq!(store.acquire(&["a"], ONE_PER_SECOND, later), Ok(()));
+    assert!(store.acquire(&["a"], ONE_PER_SECOND, later).is_err());
+}
+
+#[test]
+fn buckets_are_kept_per_key() {
+    let store = InMemoryStore::default();
+    let now = Instant::now();
+    store.acquire(&["a"], ONCE, now).unwrap();
+
+    assert_eq!(store.acquire(&["b"], ONCE, now), Ok(()));
+}
+
+#[test]
+fn buckets_are_charged_together_or_not_at_all() {
+    let store = InMemoryStore::default();
+    let now = Instant::now();
+    store.acquire(&["b"], ONCE, now).unwrap();
+
+    assert_eq!(
+        store.acquire(&["a", "b"], ONCE, now),
+        Err(Duration::from_secs(60))
+    );
+    assert_eq!(store.acquire(&["a"], ONCE, now), Ok(()));
+}
+
+#[test]
+fn rotating_the_user_id_still_charges_the_address() {
+    let limiter = limiter(ONCE);
+
+    assert_eq!(
+        limiter.check(Some("alice"), "10.0.0.1", OperationKind::Query),
+        Ok(())
+    );
+    assert_eq!(
+        limiter.check(Some("mallory"), "10.0.0.1", OperationKind::Query),
+        Err(Duration::from_secs(60))
+    );
+    assert_eq!(
+        limiter.check(None, "10.0.0.1", OperationKind::Query),
+        Err(Duration::from_secs(60))
+    );
+}
+
+#[test]
+fn the_user_bucket_applies_on_top_of_the_address() {
+    let limiter = limiter(ONCE);
+    limiter
+        .check(Some("alice"), "10.0.0.1", OperationKind::Query)
+        .unwrap();
+
+    assert_eq!(
+        limiter.check(Some("alice"), "10.0.0.2", OperationKind::Query),
+        Err(Duration::from_secs(60))
+    );
+}
+
+#[test]
+fn a_throttled_user_takes_no_token_from_the_address() {
+    let limiter = limiter(ONCE);
+    limiter
+        .check(Some("alice"), "10.0.0.1", OperationKind::Query)
+        .unwrap();
+    assert!(limiter
+        .check(Some("alice"), "10.0.0.2", OperationKind::Query)
+        .is_err());
+
+    assert_eq!(
+        limiter.check(Some("bob"), "10.0.0.2", OperationKind::Query),
+        Ok(())
+    );
+}
+
+#[test]
+fn queries_and_mutations_have_separate_buckets() {
+    let limiter = limiter(ONCE);
+    limiter
+        .check(None, "10.0.0.1", OperationKind::Query)
+        .unwrap();
+
+    assert_eq!(
+        limiter.check(None, "10.0.0.1", OperationKind::Mutation),
+        Ok(())
+    );
+}
+
+const QUERY: &str = "{ allUsers { edges { node { id } } } }";
+
+#[actix_rt::test]
+async fn empty_bucket_answers_429_with_retry_after() {
+    let app = TestApp::with_config(|c| {
+        c.limits.rate_limit.query = Budget {
+            burst: 1,
+            per_minute: 1,
+        }
+    });
+
+    let res = app.graphql(None, QUERY, json!({})).await;
+    assert_ne!(res.status, 429);
+
+    let res = app.graphql(None, QUERY, json!({})).await;
+    assert_eq!(res.status, 429);
+    assert_eq!(res.headers.get(header::RETRY_AFTER).unwrap(), "60");
+    assert_eq!(
+        res.body["errors"][0]["extensions"]["code"],
+        json!("RATE_LIMITED")
+    );
+}
+
+#[actix_rt::test]
+async fn rejected_documents_take_no_token() {
+    let app = TestApp::with_config(|c| {
+        c.limits.max_depth = 4;
+        c.limits.rate_limit.query = ONCE;
+    });
+
+    let res = app
+        .graphql(
+            None,
+            "{ allPhotos { edges { node { loadUser { id } } } } }",
+            json!({}),
+        )
+        .await;
+    assert_eq!(res.status, 400);
+    assert_eq!(
+        res.body["errors"][0]["extensions"]["code"],
+        json!("QUERY_TOO_DEEP")
+    );
+
+    let res = app.graphql(None, QUERY, json!({})).await;
+    assert_ne!(res.status, 429);
+}